@@ -0,0 +1,144 @@
+// Bitboards use the same 64-square layout as Square::to_64:
+// bit 0 is a8, bit 7 is h8, bit 56 is a1 and bit 63 is h1.
+//
+// 8   0,  1,  2,  3,  4,  5,  6,  7,
+// 7   8,  9, 10, 11, 12, 13, 14, 15,
+// 6  16, 17, 18, 19, 20, 21, 22, 23,
+// 5  24, 25, 26, 27, 28, 29, 30, 31,
+// 4  32, 33, 34, 35, 36, 37, 38, 39,
+// 3  40, 41, 42, 43, 44, 45, 46, 47,
+// 2  48, 49, 50, 51, 52, 53, 54, 55,
+// 1  56, 57, 58, 59, 60, 61, 62, 63,
+//    a   b   c   d   e   f   g   h
+
+use crate::magic;
+
+pub const FILE_A: u64 = 0x0101_0101_0101_0101;
+pub const FILE_H: u64 = FILE_A << 7;
+pub const RANK_8: u64 = 0xFF;
+pub const RANK_1: u64 = 0xFF << 56;
+// a8 and h1 are light
+pub const LIGHT_SQUARES: u64 = 0xAA55_AA55_AA55_AA55;
+
+// 0x88 offsets, so off-board detection works the same way as in movegen
+const KNIGHT_OFFSETS: [isize; 8] = [-33, -31, -18, -14, 14, 18, 31, 33];
+const KING_OFFSETS: [isize; 8] = [-17, -16, -15, -1, 1, 15, 16, 17];
+const WHITE_PAWN_OFFSETS: [isize; 2] = [-17, -15];
+const BLACK_PAWN_OFFSETS: [isize; 2] = [15, 17];
+const BISHOP_OFFSETS: [isize; 4] = [-17, -15, 15, 17];
+const ROOK_OFFSETS: [isize; 4] = [-16, -1, 1, 16];
+
+const fn square_128(square: usize) -> usize {
+    square + (square & !7)
+}
+
+const fn square_64(square: usize) -> usize {
+    (square + (square & 7)) >> 1
+}
+
+const fn leaper_attacks(offsets: &[isize]) -> [u64; 64] {
+    let mut table = [0u64; 64];
+    let mut square = 0;
+    while square < 64 {
+        let from = square_128(square);
+        let mut i = 0;
+        while i < offsets.len() {
+            let target = from.wrapping_add_signed(offsets[i]);
+            if target & 0x88 == 0 {
+                table[square] |= 1 << square_64(target);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+pub const KNIGHT_ATTACKS: [u64; 64] = leaper_attacks(&KNIGHT_OFFSETS);
+pub const KING_ATTACKS: [u64; 64] = leaper_attacks(&KING_OFFSETS);
+// squares attacked by a pawn of the given color (white, black) standing on the square
+pub const PAWN_ATTACKS: [[u64; 64]; 2] = [
+    leaper_attacks(&WHITE_PAWN_OFFSETS),
+    leaper_attacks(&BLACK_PAWN_OFFSETS),
+];
+
+// walks the rays one square at a time, only used to fill the magic tables
+pub(crate) fn sliding_attacks_slow(is_rook: bool, square: usize, occupancy: u64) -> u64 {
+    let offsets = if is_rook {
+        &ROOK_OFFSETS
+    } else {
+        &BISHOP_OFFSETS
+    };
+    let mut attacks = 0;
+    for offset in offsets {
+        let mut target = square_128(square);
+        loop {
+            target = target.wrapping_add_signed(*offset);
+            if target & 0x88 != 0 {
+                break;
+            }
+            let bit = 1 << square_64(target);
+            attacks |= bit;
+            if occupancy & bit != 0 {
+                break;
+            }
+        }
+    }
+    attacks
+}
+
+pub fn bishop_attacks(square: usize, occupancy: u64) -> u64 {
+    magic::bishop_attacks(square, occupancy)
+}
+
+pub fn rook_attacks(square: usize, occupancy: u64) -> u64 {
+    magic::rook_attacks(square, occupancy)
+}
+
+pub fn queen_attacks(square: usize, occupancy: u64) -> u64 {
+    bishop_attacks(square, occupancy) | rook_attacks(square, occupancy)
+}
+
+pub fn square_bit(square: usize) -> u64 {
+    1 << square
+}
+
+// removes the least significant set bit and returns its square
+pub fn pop_lsb(bitboard: &mut u64) -> usize {
+    let square = bitboard.trailing_zeros() as usize;
+    *bitboard &= *bitboard - 1;
+    square
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leaper_attacks() {
+        // knight on a8 attacks b6 and c7
+        assert_eq!(KNIGHT_ATTACKS[0], square_bit(17) | square_bit(10));
+        // king on h1 attacks g1, g2 and h2
+        assert_eq!(
+            KING_ATTACKS[63],
+            square_bit(62) | square_bit(54) | square_bit(55)
+        );
+        // white pawn on e2 attacks d3 and f3, black pawn on e7 attacks d6 and f6
+        assert_eq!(PAWN_ATTACKS[0][52], square_bit(43) | square_bit(45));
+        assert_eq!(PAWN_ATTACKS[1][12], square_bit(19) | square_bit(21));
+    }
+
+    #[test]
+    fn test_sliding_attacks() {
+        // rook on a1 blocked by a piece on a4, attacks the whole first rank
+        let occupancy = square_bit(32);
+        let attacks = rook_attacks(56, occupancy);
+        assert_eq!(
+            attacks,
+            (RANK_1 & !square_bit(56)) | square_bit(48) | square_bit(40) | square_bit(32)
+        );
+        // bishop on d4 in an empty board has 13 targets
+        assert_eq!(bishop_attacks(35, 0).count_ones(), 13);
+        assert_eq!(queen_attacks(35, 0).count_ones(), 27);
+    }
+}
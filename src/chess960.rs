@@ -0,0 +1,63 @@
+// Chess960 start positions numbered 0..960 with Scharnagl's scheme,
+// position 518 is the standard start position.
+
+// knight placements over the five squares left after bishops and queen
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+// the nth free file from the a-file, skipping files that already hold a piece
+fn empty_file(back_rank: &[char; 8], n: usize) -> usize {
+    (0..8)
+        .filter(|&file| back_rank[file] == ' ')
+        .nth(n)
+        .expect("enough empty files left")
+}
+
+// black's back rank for the position index, None when it is not below 960
+pub fn chess960_back_rank(index: usize) -> Option<[char; 8]> {
+    if index >= 960 {
+        return None;
+    }
+    let mut back_rank = [' '; 8];
+    let mut n = index;
+
+    // light-squared bishop on b, d, f or h file, dark-squared on a, c, e or g
+    back_rank[(n % 4) * 2 + 1] = 'b';
+    n /= 4;
+    back_rank[(n % 4) * 2] = 'b';
+    n /= 4;
+
+    back_rank[empty_file(&back_rank, n % 6)] = 'q';
+    n /= 6;
+
+    let (first, second) = KNIGHT_PLACEMENTS[n];
+    let knight_files = [first, second].map(|n| empty_file(&back_rank, n));
+    for file in knight_files {
+        back_rank[file] = 'n';
+    }
+
+    // king always ends up between the rooks
+    for piece in ['r', 'k', 'r'] {
+        back_rank[empty_file(&back_rank, 0)] = piece;
+    }
+    Some(back_rank)
+}
+
+pub fn chess960_start_fen(index: usize) -> Option<String> {
+    let black_pieces = chess960_back_rank(index)?.iter().collect::<String>();
+    let white_pieces = black_pieces.to_ascii_uppercase();
+    Some(format!(
+        "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+        black_pieces, white_pieces
+    ))
+}
@@ -0,0 +1,233 @@
+use crate::{
+    movegen::Move,
+    position::{FenError, Position},
+    san::{MoveParseError, line_to_san},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EpdError {
+    MissingFields(usize),
+    InvalidFen(FenError),
+    InvalidMove(MoveParseError),
+    InvalidOperation(String),
+    UnterminatedString,
+}
+
+impl std::fmt::Display for EpdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EpdError::MissingFields(n) => write!(f, "expected 4 position fields, found {}", n),
+            EpdError::InvalidFen(error) => write!(f, "{}", error),
+            EpdError::InvalidMove(error) => write!(f, "{}", error),
+            EpdError::InvalidOperation(s) => write!(f, "invalid operation '{}'", s),
+            EpdError::UnterminatedString => write!(f, "string operand is not closed"),
+        }
+    }
+}
+
+impl std::error::Error for EpdError {}
+
+// A position with its EPD operations. The common ones are parsed, bm, am and
+// pv into moves from their SAN, and any other opcode is kept as written.
+#[derive(Clone)]
+pub struct Epd {
+    pub position: Position,
+    // bm, am
+    pub best_moves: Vec<Move>,
+    pub avoid_moves: Vec<Move>,
+    pub id: Option<String>,
+    // c0 to c9
+    pub comments: [Option<String>; 10],
+    // ce, in centipawns for the side to move
+    pub eval: Option<i32>,
+    // acd, analysis depth in plies
+    pub depth: Option<u32>,
+    pub pv: Vec<Move>,
+    // dm, direct mate in this many moves
+    pub mate: Option<u32>,
+    pub other: Vec<(String, String)>,
+}
+
+impl Epd {
+    pub fn new(position: Position) -> Self {
+        Self {
+            position,
+            best_moves: Vec::new(),
+            avoid_moves: Vec::new(),
+            id: None,
+            comments: Default::default(),
+            eval: None,
+            depth: None,
+            pv: Vec::new(),
+            mate: None,
+            other: Vec::new(),
+        }
+    }
+
+    // 2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001";
+    pub fn parse(epd: &str) -> Result<Self, EpdError> {
+        let epd = epd.trim();
+        let mut fields = Vec::new();
+        let mut rest = epd;
+        while fields.len() < 4 {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                return Err(EpdError::MissingFields(fields.len()));
+            }
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            fields.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+        let operations = split_operations(rest)?;
+
+        // the move counters are operations in EPD
+        let counter = |opcode: &str, default: &str| {
+            operations
+                .iter()
+                .find(|(name, _)| name == opcode)
+                .map_or(default.to_string(), |(_, operands)| operands.clone())
+        };
+        let fen = format!(
+            "{} {} {}",
+            fields.join(" "),
+            counter("hmvc", "0"),
+            counter("fmvn", "1")
+        );
+        let position = Position::try_from_fen(&fen).map_err(EpdError::InvalidFen)?;
+
+        let mut epd = Epd::new(position);
+        for (opcode, operands) in operations {
+            let invalid = || EpdError::InvalidOperation(format!("{opcode} {operands}"));
+            match opcode.as_str() {
+                "bm" => epd.best_moves = parse_moves(&position, &operands)?,
+                "am" => epd.avoid_moves = parse_moves(&position, &operands)?,
+                "pv" => epd.pv = parse_line(&position, &operands)?,
+                "id" => epd.id = Some(unquote(&operands)),
+                "ce" => epd.eval = Some(operands.parse().map_err(|_| invalid())?),
+                "acd" => epd.depth = Some(operands.parse().map_err(|_| invalid())?),
+                "dm" => epd.mate = Some(operands.parse().map_err(|_| invalid())?),
+                _ => match comment_index(&opcode) {
+                    Some(index) => epd.comments[index] = Some(unquote(&operands)),
+                    None => epd.other.push((opcode, operands)),
+                },
+            }
+        }
+        Ok(epd)
+    }
+
+    pub fn to_epd(&self) -> String {
+        let fen = self.position.to_fen();
+        let mut epd = fen.split(' ').take(4).collect::<Vec<_>>().join(" ");
+        let mut push = |opcode: &str, operands: &str| {
+            epd.push_str(&format!(" {opcode} {operands};"));
+        };
+
+        let sans = |moves: &[Move]| {
+            moves
+                .iter()
+                .map(|move_| self.position.move_to_san(move_))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        if !self.best_moves.is_empty() {
+            push("bm", &sans(&self.best_moves));
+        }
+        if !self.avoid_moves.is_empty() {
+            push("am", &sans(&self.avoid_moves));
+        }
+        if let Some(mate) = self.mate {
+            push("dm", &mate.to_string());
+        }
+        if let Some(eval) = self.eval {
+            push("ce", &eval.to_string());
+        }
+        if let Some(depth) = self.depth {
+            push("acd", &depth.to_string());
+        }
+        if !self.pv.is_empty() {
+            push("pv", &line_to_san(&self.position, &self.pv));
+        }
+        if let Some(id) = &self.id {
+            push("id", &format!("\"{id}\""));
+        }
+        for (index, comment) in self.comments.iter().enumerate() {
+            if let Some(comment) = comment {
+                push(&format!("c{index}"), &format!("\"{comment}\""));
+            }
+        }
+        for (opcode, operands) in &self.other {
+            push(opcode, operands);
+        }
+        epd
+    }
+}
+
+// opcode and operands of each operation, split at the semicolons that are not
+// inside a string
+fn split_operations(text: &str) -> Result<Vec<(String, String)>, EpdError> {
+    let mut operations = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                operations.push(&text[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    if quoted {
+        return Err(EpdError::UnterminatedString);
+    }
+    // the last semicolon is sometimes left out
+    operations.push(&text[start..]);
+
+    Ok(operations
+        .into_iter()
+        .map(str::trim)
+        .filter(|operation| !operation.is_empty())
+        .map(|operation| {
+            let end = operation
+                .find(char::is_whitespace)
+                .unwrap_or(operation.len());
+            (
+                operation[..end].to_string(),
+                operation[end..].trim().to_string(),
+            )
+        })
+        .collect())
+}
+
+fn unquote(operand: &str) -> String {
+    let operand = operand.strip_prefix('"').unwrap_or(operand);
+    operand.strip_suffix('"').unwrap_or(operand).to_string()
+}
+
+fn comment_index(opcode: &str) -> Option<usize> {
+    match opcode.as_bytes() {
+        [b'c', digit @ b'0'..=b'9'] => Some((digit - b'0') as usize),
+        _ => None,
+    }
+}
+
+// moves that are each played from position
+fn parse_moves(position: &Position, operands: &str) -> Result<Vec<Move>, EpdError> {
+    operands
+        .split_whitespace()
+        .map(|san| position.parse_san(san).map_err(EpdError::InvalidMove))
+        .collect()
+}
+
+// moves played one after another from position
+fn parse_line(position: &Position, operands: &str) -> Result<Vec<Move>, EpdError> {
+    let mut position = *position;
+    let mut moves = Vec::new();
+    for san in operands.split_whitespace() {
+        let move_ = position.parse_san(san).map_err(EpdError::InvalidMove)?;
+        position.make_move(&move_);
+        moves.push(move_);
+    }
+    Ok(moves)
+}
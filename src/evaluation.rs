@@ -1,10 +1,8 @@
 use crate::{
-    movegen::{get_file, get_rank},
-    piece::{
-        BISHOP, BLACK, EMPTY, KING, KNIGHT, PAWN, QUEEN, ROOK, WHITE, get_piece_color,
-        get_piece_type,
-    },
-    position::{Position, get_square_in_64},
+    bitboard::pop_lsb,
+    piece::{Color, Piece, PieceKind},
+    position::Position,
+    square::Square,
 };
 
 const MATERIAL_PAWN: i32 = 100;
@@ -98,20 +96,20 @@ fn init_pawn_ranks(pos: &Position) -> ([u8; 10], [u8; 10]) {
     let mut white_pawn_ranks = [0u8; 10];
     let mut black_pawn_ranks = [7u8; 10];
 
-    for rank in 1..7 {
-        for file in 0..8 {
-            let square = rank * 16 + file;
-            let piece = pos.board[square];
-            if get_piece_type(piece) == PAWN {
-                let rank = get_rank(square) as u8;
-                let pawn_file_index = get_file(square) + 1;
-                let is_white = get_piece_color(piece) == WHITE;
-                if is_white && white_pawn_ranks[pawn_file_index] < rank {
-                    white_pawn_ranks[pawn_file_index] = rank
-                } else if !is_white && black_pawn_ranks[pawn_file_index] > rank {
-                    black_pawn_ranks[pawn_file_index] = rank
-                }
-            }
+    let mut white_pawns = pos.pieces(Color::White, PieceKind::Pawn);
+    while white_pawns != 0 {
+        let square = pop_lsb(&mut white_pawns);
+        let (rank, pawn_file_index) = ((square / 8) as u8, square % 8 + 1);
+        if white_pawn_ranks[pawn_file_index] < rank {
+            white_pawn_ranks[pawn_file_index] = rank
+        }
+    }
+    let mut black_pawns = pos.pieces(Color::Black, PieceKind::Pawn);
+    while black_pawns != 0 {
+        let square = pop_lsb(&mut black_pawns);
+        let (rank, pawn_file_index) = ((square / 8) as u8, square % 8 + 1);
+        if black_pawn_ranks[pawn_file_index] > rank {
+            black_pawn_ranks[pawn_file_index] = rank
         }
     }
     (white_pawn_ranks, black_pawn_ranks)
@@ -120,14 +118,14 @@ fn init_pawn_ranks(pos: &Position) -> ([u8; 10], [u8; 10]) {
 fn get_pawn_structure_score(
     white_pawn_ranks: &[u8; 10],
     black_pawn_ranks: &[u8; 10],
-    piece: u8,
+    piece: Piece,
     rank: u8,
     pawn_file: usize,
 ) -> i32 {
     let mut score = 0;
     let left_file = pawn_file - 1;
     let right_file = pawn_file + 1;
-    if get_piece_color(piece) == WHITE {
+    if piece.color() == Color::White {
         if white_pawn_ranks[pawn_file] > rank {
             score -= DOUBLED_PAWN_PENALTY;
         }
@@ -167,12 +165,12 @@ fn get_pawn_structure_score(
 fn get_rook_score(
     white_pawn_ranks: &[u8; 10],
     black_pawn_ranks: &[u8; 10],
-    piece: u8,
+    piece: Piece,
     rank: u8,
     pawn_file: usize,
 ) -> i32 {
     let mut score = 0;
-    if get_piece_color(piece) == WHITE {
+    if piece.color() == Color::White {
         if black_pawn_ranks[pawn_file] == 7 {
             if white_pawn_ranks[pawn_file] == 0 {
                 score += ROOK_OPEN_FILE_BONUS
@@ -226,93 +224,120 @@ const BISHOP_PST_BLACK: [i32; 64] = flip_board(&BISHOP_PST);
 const QUEEN_PST_BLACK: [i32; 64] = flip_board(&QUEEN_PST);
 const KING_PST_BLACK: [i32; 64] = flip_board(&KING_PST);
 
-fn get_piece_table_score(square: usize, piece: u8, piece_type: u8) -> i32 {
-    let square64 = get_square_in_64(square);
-
-    if get_piece_color(piece) == WHITE {
-        match piece_type {
-            PAWN => PAWN_PST[square64],
-            KNIGHT => KNIGHT_PST[square64],
-            BISHOP => BISHOP_PST[square64],
-            ROOK => ROOK_PST[square64],
-            QUEEN => QUEEN_PST[square64],
-            KING => KING_PST[square64],
-            _ => panic!("Unexpected piece {}", piece),
-        }
-    } else {
-        match piece_type {
-            PAWN => -PAWN_PST_BLACK[square64],
-            KNIGHT => -KNIGHT_PST_BLACK[square64],
-            BISHOP => -BISHOP_PST_BLACK[square64],
-            ROOK => -ROOK_PST_BLACK[square64],
-            QUEEN => -QUEEN_PST_BLACK[square64],
-            KING => -KING_PST_BLACK[square64],
-            _ => panic!("Unexpected piece {}", piece),
-        }
+fn get_piece_table_score(square64: usize, piece: Piece) -> i32 {
+    match piece.color() {
+        Color::White => match piece.kind() {
+            PieceKind::Pawn => PAWN_PST[square64],
+            PieceKind::Knight => KNIGHT_PST[square64],
+            PieceKind::Bishop => BISHOP_PST[square64],
+            PieceKind::Rook => ROOK_PST[square64],
+            PieceKind::Queen => QUEEN_PST[square64],
+            PieceKind::King => KING_PST[square64],
+        },
+        Color::Black => match piece.kind() {
+            PieceKind::Pawn => -PAWN_PST_BLACK[square64],
+            PieceKind::Knight => -KNIGHT_PST_BLACK[square64],
+            PieceKind::Bishop => -BISHOP_PST_BLACK[square64],
+            PieceKind::Rook => -ROOK_PST_BLACK[square64],
+            PieceKind::Queen => -QUEEN_PST_BLACK[square64],
+            PieceKind::King => -KING_PST_BLACK[square64],
+        },
     }
 }
 
-pub fn get_material_score(piece: u8) -> i32 {
-    match get_piece_type(piece) {
-        PAWN => MATERIAL_PAWN,
-        KNIGHT => MATERIAL_KNIGHT,
-        BISHOP => MATERIAL_BISHOP,
-        ROOK => MATERIAL_ROOK,
-        QUEEN => MATERIAL_QUEEN,
-        KING => MATERIAL_KING,
-        EMPTY => 0,
-        _ => panic!("{}", get_piece_type(piece)),
+pub fn get_material_score(piece_type: PieceKind) -> i32 {
+    match piece_type {
+        PieceKind::Pawn => MATERIAL_PAWN,
+        PieceKind::Knight => MATERIAL_KNIGHT,
+        PieceKind::Bishop => MATERIAL_BISHOP,
+        PieceKind::Rook => MATERIAL_ROOK,
+        PieceKind::Queen => MATERIAL_QUEEN,
+        PieceKind::King => MATERIAL_KING,
     }
 }
 
-fn get_piece_material_score(piece: u8) -> i32 {
-    let side = match get_piece_color(piece) {
-        WHITE => 1,
-        BLACK => -1,
-        EMPTY => 0,
-        _ => panic!("{}", get_piece_color(piece)),
+fn get_piece_material_score(piece: Piece) -> i32 {
+    let side = match piece.color() {
+        Color::White => 1,
+        Color::Black => -1,
+    };
+    side * get_material_score(piece.kind())
+}
+
+fn get_non_pawn_material(position: &Position, color: Color) -> i32 {
+    [
+        PieceKind::Knight,
+        PieceKind::Bishop,
+        PieceKind::Rook,
+        PieceKind::Queen,
+    ]
+    .iter()
+    .map(|&kind| position.pieces(color, kind).count_ones() as i32 * get_material_score(kind))
+    .sum()
+}
+
+// Without pawns the side ahead needs more than a minor piece's worth of extra
+// material to win, so those endings are pulled toward a draw, dead ones to zero.
+fn scale_drawish(position: &Position, score: i32) -> i32 {
+    if position.is_material_draw() {
+        return 0;
+    }
+    let stronger = if score > 0 {
+        Color::White
+    } else {
+        Color::Black
     };
-    let material_score = get_material_score(piece);
-    side * material_score
+    if position.pieces(stronger, PieceKind::Pawn) != 0 {
+        return score;
+    }
+    let advantage =
+        get_non_pawn_material(position, stronger) - get_non_pawn_material(position, !stronger);
+    if advantage <= MATERIAL_BISHOP {
+        score / 8
+    } else {
+        score
+    }
 }
 
 pub fn evaluate(position: &Position) -> i32 {
     let mut score = 0;
-    let side = if position.is_white_turn { 1 } else { -1 };
+    let side = match position.side_to_move {
+        Color::White => 1,
+        Color::Black => -1,
+    };
     let (white_pawn_ranks, black_pawn_ranks) = init_pawn_ranks(position);
 
-    for rank in 0..8 {
-        for file in 0..8 {
-            let square = rank * 16 + file;
-            let piece = position.board[square];
-            let piece_type = get_piece_type(piece);
-            if piece_type == EMPTY {
-                continue;
-            }
+    let mut pieces = position.occupied();
+    while pieces != 0 {
+        let square = pop_lsb(&mut pieces);
+        let (rank, file) = (square / 8, square % 8);
+        let Some(piece) = position.piece_at(Square::from_64(square)) else {
+            continue;
+        };
+        let piece_type = piece.kind();
 
-            score += get_piece_table_score(square, piece, piece_type);
-            score += get_piece_material_score(piece);
-            if piece_type == PAWN {
-                score += get_pawn_structure_score(
-                    &white_pawn_ranks,
-                    &black_pawn_ranks,
-                    piece,
-                    rank as u8,
-                    file + 1,
-                );
-            }
-            if piece_type == ROOK {
-                score += get_rook_score(
-                    &white_pawn_ranks,
-                    &black_pawn_ranks,
-                    piece,
-                    rank as u8,
-                    file + 1,
-                );
-            }
+        score += get_piece_table_score(square, piece);
+        score += get_piece_material_score(piece);
+        if piece_type == PieceKind::Pawn {
+            score += get_pawn_structure_score(
+                &white_pawn_ranks,
+                &black_pawn_ranks,
+                piece,
+                rank as u8,
+                file + 1,
+            );
+        }
+        if piece_type == PieceKind::Rook {
+            score += get_rook_score(
+                &white_pawn_ranks,
+                &black_pawn_ranks,
+                piece,
+                rank as u8,
+                file + 1,
+            );
         }
     }
-    score * side
+    scale_drawish(position, score) * side
 }
 
 #[cfg(test)]
@@ -335,4 +360,25 @@ mod tests {
         let rook_eval = evaluate(&rook_pos);
         assert_eq!(rook_eval, 0);
     }
+
+    #[test]
+    fn test_drawish_material_is_scaled() {
+        // a lone minor piece or two knights can't win
+        for fen in [
+            "8/8/4k3/8/8/3KN3/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3KNN2/8/8 b - - 0 1",
+            "8/2b5/4k3/8/8/3K4/8/B7 w - - 0 1",
+        ] {
+            assert_eq!(evaluate(&Position::from_fen(fen)), 0, "{fen}");
+        }
+
+        // rook against bishop is close to a draw, rook against nothing is not
+        let rook_bishop = evaluate(&Position::from_fen("8/8/4k3/8/8/3KR3/8/6b1 w - - 0 1"));
+        assert!((0..100).contains(&rook_bishop));
+        let rook = evaluate(&Position::from_fen("8/8/4k3/8/8/3KR3/8/8 b - - 0 1"));
+        assert!(rook < -400);
+        // with a pawn left the extra minor piece still counts
+        let bishop_pawn = evaluate(&Position::from_fen("8/8/4k3/8/8/3KB3/6P1/8 w - - 0 1"));
+        assert!(bishop_pawn > 350);
+    }
 }
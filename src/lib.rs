@@ -1,11 +1,21 @@
+pub mod bitboard;
+pub mod chess960;
+pub mod epd;
 pub mod evaluation;
 pub mod hash;
+pub mod magic;
 pub mod movegen;
+pub mod movelist;
 pub mod moveordering;
+pub mod movepicker;
 pub mod perft;
+pub mod pgn;
 pub mod piece;
 pub mod position;
+pub mod san;
 pub mod search;
+pub mod see;
+pub mod square;
 pub mod uci;
 
 pub const START_POSITION_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
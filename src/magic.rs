@@ -0,0 +1,141 @@
+use std::sync::LazyLock;
+
+use crate::bitboard::{FILE_A, FILE_H, RANK_1, RANK_8, sliding_attacks_slow};
+use crate::hash::Xorshift64;
+
+// Magic bitboards: the relevant blockers of a slider are multiplied by a magic
+// number so that the top bits of the product index a precomputed attack set.
+// Magics are searched at first use with a fixed seed, so tables are the same on every run.
+
+const BISHOP: usize = 0;
+const ROOK: usize = 1;
+
+#[derive(Default, Clone, Copy)]
+struct Magic {
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupancy: u64) -> usize {
+        self.offset + ((occupancy & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct MagicTables {
+    magics: [[Magic; 64]; 2], // bishop, rook
+    attacks: Vec<u64>,
+}
+
+static MAGIC_TABLES: LazyLock<MagicTables> = LazyLock::new(MagicTables::new);
+
+fn relevant_occupancy_mask(slider: usize, square: usize) -> u64 {
+    // edge squares never block anything behind them, unless the slider is on that edge
+    let rank = RANK_8 << (square & !7);
+    let file = FILE_A << (square & 7);
+    let edges = ((RANK_1 | RANK_8) & !rank) | ((FILE_A | FILE_H) & !file);
+    sliding_attacks_slow(slider == ROOK, square, 0) & !edges
+}
+
+impl MagicTables {
+    fn new() -> Self {
+        const SEED: u64 = 0x4D595DF4D0F33173;
+        let mut rng = Xorshift64::new(SEED);
+        let mut tables = Self {
+            magics: [[Magic::default(); 64]; 2],
+            attacks: Vec::new(),
+        };
+
+        for slider in [BISHOP, ROOK] {
+            for square in 0..64 {
+                let mask = relevant_occupancy_mask(slider, square);
+                let bits = mask.count_ones();
+
+                // enumerate every subset of the mask (carry-rippler)
+                let mut occupancies = Vec::with_capacity(1 << bits);
+                let mut reference = Vec::with_capacity(1 << bits);
+                let mut subset = 0u64;
+                loop {
+                    occupancies.push(subset);
+                    reference.push(sliding_attacks_slow(slider == ROOK, square, subset));
+                    subset = subset.wrapping_sub(mask) & mask;
+                    if subset == 0 {
+                        break;
+                    }
+                }
+
+                let offset = tables.attacks.len();
+                tables.attacks.resize(offset + (1 << bits), 0);
+                let mut magic = Magic {
+                    mask,
+                    magic: 0,
+                    shift: 64 - bits,
+                    offset,
+                };
+                // epoch marks which entries were written by the current candidate
+                let mut epoch = vec![0u32; 1 << bits];
+                let mut attempt = 0;
+                loop {
+                    attempt += 1;
+                    magic.magic = rng.next() & rng.next() & rng.next();
+                    if (mask.wrapping_mul(magic.magic) >> 56).count_ones() < 6 {
+                        continue;
+                    }
+                    let mut found = true;
+                    for (occupancy, attacks) in occupancies.iter().zip(&reference) {
+                        let idx = magic.index(*occupancy);
+                        if epoch[idx - offset] < attempt {
+                            epoch[idx - offset] = attempt;
+                            tables.attacks[idx] = *attacks;
+                        } else if tables.attacks[idx] != *attacks {
+                            found = false;
+                            break;
+                        }
+                    }
+                    if found {
+                        break;
+                    }
+                }
+                tables.magics[slider][square] = magic;
+            }
+        }
+        tables
+    }
+}
+
+pub fn bishop_attacks(square: usize, occupancy: u64) -> u64 {
+    let tables = &*MAGIC_TABLES;
+    tables.attacks[tables.magics[BISHOP][square].index(occupancy)]
+}
+
+pub fn rook_attacks(square: usize, occupancy: u64) -> u64 {
+    let tables = &*MAGIC_TABLES;
+    tables.attacks[tables.magics[ROOK][square].index(occupancy)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::square_bit;
+
+    #[test]
+    fn test_magic_attacks_match_ray_walk() {
+        let mut rng = Xorshift64::new(0x9E3779B97F4A7C15);
+        for _ in 0..64 {
+            let occupancy = rng.next() & rng.next();
+            for square in 0..64 {
+                let occupancy = occupancy & !square_bit(square);
+                assert_eq!(
+                    bishop_attacks(square, occupancy),
+                    sliding_attacks_slow(false, square, occupancy)
+                );
+                assert_eq!(
+                    rook_attacks(square, occupancy),
+                    sliding_attacks_slow(true, square, occupancy)
+                );
+            }
+        }
+    }
+}
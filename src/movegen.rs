@@ -1,530 +1,762 @@
-use crate::piece::*;
+use crate::bitboard::*;
+use crate::movelist::MoveList;
+use crate::piece::{Color, PieceKind};
 use crate::position::Position;
+use crate::square::Square;
 
+// Readable view of a move, see Move::decode and DecodedMove::encode.
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
-pub struct Move {
-    pub from: usize,
-    pub to: usize,
-    pub promoted_piece: Option<u8>,
+pub struct DecodedMove {
+    pub from: Square,
+    pub to: Square,
+    pub promoted_piece: Option<PieceKind>,
     pub is_capture: bool,
     pub is_enpassant: bool,
     pub is_double_pawn: bool,
     pub is_castling: bool,
 }
 
-const N: isize = -16;
-const S: isize = 16;
-const E: isize = 1;
-const W: isize = -1;
+// Move packed in 16 bits: from square in bits 0-5, to square in bits 6-11
+// (64-square indexes) and a 4-bit flag in bits 12-15:
+//
+// 0000 quiet         0100 capture
+// 0001 double pawn   0101 en passant
+// 0010 castling      1000..1011 promotion to n, b, r, q
+//                    1100..1111 capturing promotion to n, b, r, q
+//
+// Castling is encoded as king takes own rook, like in UCI_Chess960.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Move(u16);
+
+const DOUBLE_PAWN_FLAG: u16 = 0b0001;
+const CASTLING_FLAG: u16 = 0b0010;
+const CAPTURE_FLAG: u16 = 0b0100;
+const ENPASSANT_FLAG: u16 = 0b0101;
+const PROMOTION_FLAG: u16 = 0b1000;
+
+impl Move {
+    const fn new(from: Square, to: Square, flag: u16) -> Move {
+        Move(from.to_64() as u16 | (to.to_64() as u16) << 6 | flag << 12)
+    }
+
+    pub const fn from(self) -> Square {
+        Square::from_64((self.0 & 0x3F) as usize)
+    }
 
-const PAWN_MOVES: &[isize] = &[N, N + N, N + W, N + E];
-const KNIGHT_MOVES: &[isize] = &[
-    N + N + E,
-    E + E + N,
-    E + E + S,
-    S + S + E,
-    S + S + W,
-    W + W + S,
-    W + W + N,
-    N + N + W,
-];
-const BISHOP_MOVES: &[isize] = &[N + E, E + S, S + W, W + N];
-const ROOK_MOVES: &[isize] = &[N, E, S, W];
-const QUEEN_KING_MOVES: &[isize] = &[N, N + E, E, E + S, S, S + W, W, W + N];
+    pub const fn to(self) -> Square {
+        Square::from_64((self.0 >> 6 & 0x3F) as usize)
+    }
 
-pub const BOARD_SQUARES: [usize; 64] = {
-    let mut squares = [0usize; 64];
-    let mut i = 0;
-    while i < 64 {
-        let rank = i / 8;
-        let file = i % 8;
-        squares[i] = 16 * rank + file;
-        i += 1;
+    const fn flag(self) -> u16 {
+        self.0 >> 12
     }
-    squares
-};
 
-fn get_piece_move_patterns(piece: u8) -> &'static [isize] {
-    match get_piece_type(piece) {
-        PAWN => PAWN_MOVES,
-        KNIGHT => KNIGHT_MOVES,
-        BISHOP => BISHOP_MOVES,
-        ROOK => ROOK_MOVES,
-        QUEEN | KING => QUEEN_KING_MOVES,
-        _ => &[],
+    pub const fn promoted_piece(self) -> Option<PieceKind> {
+        if self.flag() & PROMOTION_FLAG == 0 {
+            return None;
+        }
+        match self.flag() & 0b11 {
+            0 => Some(PieceKind::Knight),
+            1 => Some(PieceKind::Bishop),
+            2 => Some(PieceKind::Rook),
+            _ => Some(PieceKind::Queen),
+        }
+    }
+
+    pub const fn is_capture(self) -> bool {
+        self.flag() & CAPTURE_FLAG != 0
+    }
+
+    pub const fn is_enpassant(self) -> bool {
+        self.flag() == ENPASSANT_FLAG
+    }
+
+    pub const fn is_double_pawn(self) -> bool {
+        self.flag() == DOUBLE_PAWN_FLAG
+    }
+
+    pub const fn is_castling(self) -> bool {
+        self.flag() == CASTLING_FLAG
+    }
+
+    pub const fn is_promotion(self) -> bool {
+        self.flag() & PROMOTION_FLAG != 0
+    }
+
+    // captures, en passant and promotions, the moves quiescence looks at
+    pub const fn is_tactical(self) -> bool {
+        self.is_capture() || self.is_promotion()
+    }
+
+    // the raw 16 bits, e.g. for storing moves outside the engine
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u16) -> Move {
+        Move(bits)
+    }
+
+    pub fn decode(self) -> DecodedMove {
+        DecodedMove {
+            from: self.from(),
+            to: self.to(),
+            promoted_piece: self.promoted_piece(),
+            is_capture: self.is_capture(),
+            is_enpassant: self.is_enpassant(),
+            is_double_pawn: self.is_double_pawn(),
+            is_castling: self.is_castling(),
+        }
     }
 }
 
-pub fn is_off_board(index: usize) -> bool {
-    index & 0x88 != 0
+const fn promotion_flag(kind: PieceKind) -> u16 {
+    PROMOTION_FLAG
+        | match kind {
+            PieceKind::Knight => 0,
+            PieceKind::Bishop => 1,
+            PieceKind::Rook => 2,
+            _ => 3,
+        }
 }
 
-#[allow(dead_code)]
-// get file 0..7
-pub fn get_file(square: usize) -> usize {
-    square & 7
+impl DecodedMove {
+    pub fn encode(&self) -> Move {
+        let mut flag = 0;
+        if self.is_capture {
+            flag |= CAPTURE_FLAG;
+        }
+        if self.is_enpassant {
+            flag = ENPASSANT_FLAG;
+        } else if self.is_double_pawn {
+            flag = DOUBLE_PAWN_FLAG;
+        } else if self.is_castling {
+            flag = CASTLING_FLAG;
+        }
+        if let Some(kind) = self.promoted_piece {
+            flag |= promotion_flag(kind);
+        }
+        Move::new(self.from, self.to, flag)
+    }
 }
 
-// get rank 0..7
-pub fn get_rank(square: usize) -> usize {
-    square >> 4
+// king and rook destination squares of a castling move (king takes own rook)
+pub fn get_castling_targets(move_: &Move) -> (Square, Square) {
+    let rank = move_.from().rank();
+    if move_.to().file() > move_.from().file() {
+        (
+            Square::from_file_rank(6, rank),
+            Square::from_file_rank(5, rank),
+        )
+    } else {
+        (
+            Square::from_file_rank(2, rank),
+            Square::from_file_rank(3, rank),
+        )
+    }
 }
 
-pub fn get_square_string(square: usize) -> String {
-    if is_off_board(square) {
-        panic!("Square {} is off board!", square);
+// UCI_Chess960 notation, castling is written as king takes rook
+pub fn get_chess960_move_string(move_: &Move) -> String {
+    match move_.promoted_piece() {
+        Some(kind) => format!("{}{}{}", move_.from(), move_.to(), kind.to_char()),
+        None => format!("{}{}", move_.from(), move_.to()),
     }
-    let ranks = "87654321";
-    let files = "abcdefgh";
-    let rank = get_rank(square);
-    let file = get_file(square);
-    let rank_char = ranks.chars().nth(rank).unwrap();
-    let file_char = files.chars().nth(file).unwrap();
-    format!("{}{}", file_char, rank_char)
 }
 
 pub fn get_move_string(move_: &Move) -> String {
-    let prom_str = match move_.promoted_piece.map(get_piece_type) {
-        Some(KNIGHT) => "n",
-        Some(BISHOP) => "b",
-        Some(ROOK) => "r",
-        Some(QUEEN) => "q",
-        Some(other) => unreachable!("unexpected promotion piece: {}", other),
-        None => "",
-    };
+    if move_.is_castling() {
+        let (king_to, _) = get_castling_targets(move_);
+        return format!("{}{}", move_.from(), king_to);
+    }
+    get_chess960_move_string(move_)
+}
 
-    format!(
-        "{}{}{}",
-        get_square_string(move_.from),
-        get_square_string(move_.to),
-        prom_str
-    )
+// pieces of the given color attacking square (64-square index) with the given occupancy
+pub(crate) fn attackers(position: &Position, square: usize, occupancy: u64, by: Color) -> u64 {
+    let queens = position.pieces(by, PieceKind::Queen);
+    // a pawn of the other color on the square attacks exactly the pawns attacking it
+    (PAWN_ATTACKS[(!by).index()][square] & position.pieces(by, PieceKind::Pawn))
+        | (KNIGHT_ATTACKS[square] & position.pieces(by, PieceKind::Knight))
+        | (KING_ATTACKS[square] & position.pieces(by, PieceKind::King))
+        | (bishop_attacks(square, occupancy) & (position.pieces(by, PieceKind::Bishop) | queens))
+        | (rook_attacks(square, occupancy) & (position.pieces(by, PieceKind::Rook) | queens))
 }
 
-pub fn is_square_attacked(square: usize, position: &Position) -> bool {
-    // pawn attacks
-    if position.is_white_turn {
-        for pattern in [-15, -17] {
-            let attack = square.wrapping_add_signed(pattern);
-            if is_off_board(attack) {
-                continue;
-            }
-            let attack_piece = position.board[attack];
-            if attack_piece == BLACK | PAWN {
-                return true;
-            }
-        }
+pub fn is_square_attacked(square: Square, position: &Position) -> bool {
+    position.attackers_to(square, !position.side_to_move) != 0
+}
+
+// squares strictly between a and b if they share a rank, file or diagonal
+fn between(a: usize, b: usize) -> u64 {
+    let (bit_a, bit_b) = (square_bit(a), square_bit(b));
+    if rook_attacks(a, 0) & bit_b != 0 {
+        rook_attacks(a, bit_b) & rook_attacks(b, bit_a)
+    } else if bishop_attacks(a, 0) & bit_b != 0 {
+        bishop_attacks(a, bit_b) & bishop_attacks(b, bit_a)
     } else {
-        for pattern in [15, 17] {
-            let attack = square.wrapping_add_signed(pattern);
-            if is_off_board(attack) {
-                continue;
-            }
-            let attack_piece = position.board[attack];
-            if attack_piece == WHITE | PAWN {
-                return true;
-            }
-        }
+        0
     }
-    // knight attacks
-    for pattern in KNIGHT_MOVES {
-        let attack = square.wrapping_add_signed(*pattern);
-        if is_off_board(attack) {
-            continue;
-        }
-        let attack_piece = position.board[attack];
-        if position.is_white_turn && attack_piece == BLACK | KNIGHT {
-            return true;
-        }
-        if !position.is_white_turn && attack_piece == WHITE | KNIGHT {
-            return true;
-        }
+}
+
+// the whole line through a and b, edge to edge
+fn line(a: usize, b: usize) -> u64 {
+    let (bit_a, bit_b) = (square_bit(a), square_bit(b));
+    if rook_attacks(a, 0) & bit_b != 0 {
+        (rook_attacks(a, 0) & rook_attacks(b, 0)) | bit_a | bit_b
+    } else if bishop_attacks(a, 0) & bit_b != 0 {
+        (bishop_attacks(a, 0) & bishop_attacks(b, 0)) | bit_a | bit_b
+    } else {
+        0
     }
-    // king attacks
-    for pattern in QUEEN_KING_MOVES {
-        let attack = square.wrapping_add_signed(*pattern);
-        if is_off_board(attack) {
-            continue;
-        }
-        let attack_piece = position.board[attack];
-        if position.is_white_turn && attack_piece == BLACK | KING {
-            return true;
-        }
-        if !position.is_white_turn && attack_piece == WHITE | KING {
-            return true;
+}
+
+// pieces of either color standing alone between the king and a slider of the given color
+pub(crate) fn sole_blockers(position: &Position, king: usize, sliders: Color) -> u64 {
+    // sliders that would see the king on an empty board
+    let queens = position.pieces(sliders, PieceKind::Queen);
+    let mut snipers = (rook_attacks(king, 0)
+        & (position.pieces(sliders, PieceKind::Rook) | queens))
+        | (bishop_attacks(king, 0) & (position.pieces(sliders, PieceKind::Bishop) | queens));
+    let mut blockers = 0;
+    while snipers != 0 {
+        let sniper = pop_lsb(&mut snipers);
+        let between = between(king, sniper) & position.occupied();
+        if between.count_ones() == 1 {
+            blockers |= between;
         }
     }
-    // bishop and queen attacks
-    for pattern in BISHOP_MOVES {
-        let mut attack = square.wrapping_add_signed(*pattern);
-        while !is_off_board(attack) {
-            let attack_piece = position.board[attack];
-            if position.is_white_turn
-                && (attack_piece == BLACK | BISHOP || attack_piece == BLACK | QUEEN)
-            {
-                return true;
-            }
-            if !position.is_white_turn
-                && (attack_piece == WHITE | BISHOP || attack_piece == WHITE | QUEEN)
-            {
-                return true;
-            }
-            if attack_piece != EMPTY {
-                break;
-            }
-            attack = attack.wrapping_add_signed(*pattern);
+    blockers
+}
+
+// Computed once per generation: which squares a non-king move has to land on
+// to deal with a check, and which of our pieces can only move along a pin.
+struct Restrictions {
+    king: usize,
+    checkers: u64,
+    check_mask: u64,
+    pinned: u64,
+}
+
+impl Restrictions {
+    fn new(position: &Position) -> Self {
+        let us = position.side_to_move;
+        let king = position.king_squares[us.index()].to_64();
+        let checkers = position.checkers();
+
+        let check_mask = match checkers.count_ones() {
+            0 => u64::MAX,
+            // capture the checker or block a sliding check
+            1 => checkers | between(king, checkers.trailing_zeros() as usize),
+            // double check, only the king can move
+            _ => 0,
+        };
+
+        let pinned = position.pinned(us);
+
+        Self {
+            king,
+            checkers,
+            check_mask,
+            pinned,
         }
     }
-    // rook and queen attacks
-    for pattern in ROOK_MOVES {
-        let mut attack = square.wrapping_add_signed(*pattern);
-        while !is_off_board(attack) {
-            let attack_piece = position.board[attack];
-            if position.is_white_turn
-                && (attack_piece == BLACK | ROOK || attack_piece == BLACK | QUEEN)
-            {
-                return true;
-            }
-            if !position.is_white_turn
-                && (attack_piece == WHITE | ROOK || attack_piece == WHITE | QUEEN)
-            {
-                return true;
-            }
-            if attack_piece != EMPTY {
-                break;
-            }
-            attack = attack.wrapping_add_signed(*pattern);
+
+    // target squares allowed for the piece on square, apart from the king
+    fn allowed_targets(&self, square: usize) -> u64 {
+        if self.pinned & square_bit(square) != 0 {
+            self.check_mask & line(self.king, square)
+        } else {
+            self.check_mask
         }
     }
+}
+
+// squares attacked by a non-pawn piece standing on square (64-square index)
+fn get_piece_attacks(piece_type: PieceKind, square: usize, occupancy: u64) -> u64 {
+    match piece_type {
+        PieceKind::Knight => KNIGHT_ATTACKS[square],
+        PieceKind::Bishop => bishop_attacks(square, occupancy),
+        PieceKind::Rook => rook_attacks(square, occupancy),
+        PieceKind::Queen => queen_attacks(square, occupancy),
+        PieceKind::King => KING_ATTACKS[square],
+        PieceKind::Pawn => 0,
+    }
+}
 
-    false
+fn push_moves(from: usize, mut targets: u64, is_capture: bool, moves: &mut MoveList) {
+    while targets != 0 {
+        let to = pop_lsb(&mut targets);
+        let flag = if is_capture { CAPTURE_FLAG } else { 0 };
+        moves.push(Move::new(Square::from_64(from), Square::from_64(to), flag));
+    }
 }
 
-fn generate_sliding_moves(
+fn generate_piece_moves(
     square: usize,
+    piece_type: PieceKind,
     position: &Position,
-    moves: &mut Vec<Move>,
-    only_tactical_moves: bool,
+    restrictions: &Restrictions,
+    moves: &mut MoveList,
+    gen_type: GenType,
 ) {
-    let piece = position.board[square];
-    for pattern in get_piece_move_patterns(piece) {
-        let mut target_square = square;
-        loop {
-            target_square = target_square.wrapping_add_signed(*pattern);
-            if is_off_board(target_square) {
-                break;
-            }
-            let target_piece = position.board[target_square];
-            if get_piece_color(piece) == get_piece_color(target_piece) {
-                break;
-            } else if target_piece != EMPTY {
-                moves.push(Move {
-                    from: square,
-                    to: target_square,
-                    promoted_piece: None,
-                    is_capture: true,
-                    is_enpassant: false,
-                    is_double_pawn: false,
-                    is_castling: false,
-                });
-                break;
-            } else if !only_tactical_moves {
-                moves.push(Move {
-                    from: square,
-                    to: target_square,
-                    promoted_piece: None,
-                    is_capture: false,
-                    is_enpassant: false,
-                    is_double_pawn: false,
-                    is_castling: false,
-                });
-            }
-        }
+    let them = !position.side_to_move;
+    let occupancy = position.occupied();
+    let attacks =
+        get_piece_attacks(piece_type, square, occupancy) & restrictions.allowed_targets(square);
+
+    if gen_type.includes_tactical() {
+        push_moves(
+            square,
+            attacks & position.occupancy[them.index()],
+            true,
+            moves,
+        );
+    }
+    if gen_type.includes_quiet() {
+        push_moves(square, attacks & !occupancy, false, moves);
     }
 }
 
-fn generate_crawling_moves(
-    square: usize,
+fn generate_king_moves(
     position: &Position,
-    moves: &mut Vec<Move>,
-    only_tactical_moves: bool,
+    restrictions: &Restrictions,
+    moves: &mut MoveList,
+    gen_type: GenType,
 ) {
-    let piece = position.board[square];
-
-    if get_piece_type(piece) == KING && !only_tactical_moves {
-        if position.is_white_turn {
-            if position.castling_rights[0]
-                && position.board[square + 1] == EMPTY
-                && position.board[square + 2] == EMPTY
-                && !is_square_attacked(square, position)
-                && !is_square_attacked(square + 1, position)
-            {
-                moves.push(Move {
-                    from: square,
-                    to: square + 2,
-                    promoted_piece: None,
-                    is_capture: false,
-                    is_enpassant: false,
-                    is_double_pawn: false,
-                    is_castling: true,
-                });
-            }
-            if position.castling_rights[1]
-                && position.board[square - 1] == EMPTY
-                && position.board[square - 2] == EMPTY
-                && position.board[square - 3] == EMPTY
-                && !is_square_attacked(square, position)
-                && !is_square_attacked(square - 1, position)
-            {
-                moves.push(Move {
-                    from: square,
-                    to: square - 2,
-                    promoted_piece: None,
-                    is_capture: false,
-                    is_enpassant: false,
-                    is_double_pawn: false,
-                    is_castling: true,
-                });
-            }
+    let us = position.side_to_move;
+    let king = restrictions.king;
+    // the king doesn't block attacks on squares behind it, so take it off the board
+    let occupancy = position.occupied() ^ square_bit(king);
+    let mut targets = KING_ATTACKS[king] & !position.occupancy[us.index()];
+    if !gen_type.includes_tactical() {
+        targets &= !position.occupancy[(!us).index()];
+    }
+    if !gen_type.includes_quiet() {
+        targets &= position.occupancy[(!us).index()];
+    }
+    while targets != 0 {
+        let to = pop_lsb(&mut targets);
+        if attackers(position, to, occupancy, !us) == 0 {
+            let is_capture = position.occupancy[(!us).index()] & square_bit(to) != 0;
+            push_moves(king, square_bit(to), is_capture, moves);
         }
+    }
+}
 
-        if !position.is_white_turn {
-            if position.castling_rights[2]
-                && position.board[square + 1] == EMPTY
-                && position.board[square + 2] == EMPTY
-                && !is_square_attacked(square, position)
-                && !is_square_attacked(square + 1, position)
-            {
-                moves.push(Move {
-                    from: square,
-                    to: square + 2,
-                    promoted_piece: None,
-                    is_capture: false,
-                    is_enpassant: false,
-                    is_double_pawn: false,
-                    is_castling: true,
-                });
-            }
+// squares from a to b inclusive, both on the same rank (64-square indexes)
+fn rank_span(a: usize, b: usize) -> u64 {
+    let (low, high) = (a.min(b), a.max(b));
+    (u64::MAX >> (63 - high)) & (u64::MAX << low)
+}
 
-            if position.castling_rights[3]
-                && position.board[square - 1] == EMPTY
-                && position.board[square - 2] == EMPTY
-                && position.board[square - 3] == EMPTY
-                && !is_square_attacked(square, position)
-                && !is_square_attacked(square - 1, position)
-            {
-                moves.push(Move {
-                    from: square,
-                    to: square - 2,
-                    promoted_piece: None,
-                    is_capture: false,
-                    is_enpassant: false,
-                    is_double_pawn: false,
-                    is_castling: true,
-                });
-            }
-        }
-    }
+fn generate_castling_moves(position: &Position, moves: &mut MoveList) {
+    let us = position.side_to_move;
+    let first_idx = us.index() * 2;
+    let rights = [first_idx, first_idx + 1];
+    let square = position.king_squares[us.index()];
+    let occupancy = position.occupied();
 
-    for pattern in get_piece_move_patterns(piece) {
-        let target_square = square.wrapping_add_signed(*pattern);
-        if is_off_board(target_square) {
+    for idx in rights {
+        if !position.castling_rights[idx] {
             continue;
         }
-        let target_piece = position.board[target_square];
-        if get_piece_color(piece) == get_piece_color(target_piece) {
+        let rook_square = position.castling_rooks[idx];
+        let move_ = Move::new(square, rook_square, CASTLING_FLAG);
+        let (king_to, rook_to) = get_castling_targets(&move_);
+        let (king_from, king_to) = (square.to_64(), king_to.to_64());
+        let (rook_from, rook_to) = (rook_square.to_64(), rook_to.to_64());
+
+        // king and rook can only pass over each other and empty squares
+        let path = rank_span(king_from, king_to) | rank_span(rook_from, rook_to);
+        let blockers = occupancy & !square_bit(king_from) & !square_bit(rook_from);
+        if path & blockers != 0 {
             continue;
-        } else if target_piece != EMPTY {
-            moves.push(Move {
-                from: square,
-                to: target_square,
-                promoted_piece: None,
-                is_capture: true,
-                is_enpassant: false,
-                is_double_pawn: false,
-                is_castling: false,
-            });
-        } else if !only_tactical_moves {
-            moves.push(Move {
-                from: square,
-                to: target_square,
-                promoted_piece: None,
-                is_capture: false,
-                is_enpassant: false,
-                is_double_pawn: false,
-                is_castling: false,
-            });
+        }
+        // king can't castle out of, through or into check, and in Chess960
+        // the castling rook may be the piece shielding the king's destination
+        let occupancy = occupancy ^ square_bit(rook_from);
+        let mut king_path = rank_span(king_from, king_to);
+        let mut is_attacked = false;
+        while king_path != 0 && !is_attacked {
+            let square = pop_lsb(&mut king_path);
+            is_attacked = attackers(position, square, occupancy, !us) != 0;
+        }
+        if !is_attacked {
+            moves.push(move_);
         }
     }
 }
 
+fn push_pawn_moves(
+    from: usize,
+    mut targets: u64,
+    is_capture: bool,
+    promotion_rank: u64,
+    moves: &mut MoveList,
+) {
+    let flag = if is_capture { CAPTURE_FLAG } else { 0 };
+    while targets != 0 {
+        let to = pop_lsb(&mut targets);
+        let (from, to_square) = (Square::from_64(from), Square::from_64(to));
+        if square_bit(to) & promotion_rank != 0 {
+            for prom_piece in [
+                PieceKind::Queen,
+                PieceKind::Knight,
+                PieceKind::Rook,
+                PieceKind::Bishop,
+            ] {
+                moves.push(Move::new(
+                    from,
+                    to_square,
+                    flag | promotion_flag(prom_piece),
+                ));
+            }
+        } else {
+            moves.push(Move::new(from, to_square, flag));
+        }
+    }
+}
+
+// En passant removes two pawns from the same rank at once, which can expose the king
+// to a slider even when neither pawn is pinned on its own, so check the end result.
+fn is_legal_enpassant(position: &Position, from: usize, to: usize, captured: usize) -> bool {
+    let us = position.side_to_move;
+    let them = !us;
+    let king = position.king_squares[us.index()].to_64();
+    let occupancy =
+        (position.occupied() ^ square_bit(from) ^ square_bit(captured)) | square_bit(to);
+    let queens = position.pieces(them, PieceKind::Queen);
+    bishop_attacks(king, occupancy) & (position.pieces(them, PieceKind::Bishop) | queens) == 0
+        && rook_attacks(king, occupancy) & (position.pieces(them, PieceKind::Rook) | queens) == 0
+}
+
 fn generate_pawn_moves(
     square: usize,
     position: &Position,
-    moves: &mut Vec<Move>,
-    only_tactical_moves: bool,
+    restrictions: &Restrictions,
+    moves: &mut MoveList,
+    gen_type: GenType,
 ) {
-    let is_white = position.is_white_turn;
-    let piece = position.board[square];
-
-    // Direction constants based on color
-    let (forward, rank_for_double_move, promotion_rank) =
-        if is_white { (N, 6, 0) } else { (S, 1, 7) };
+    let us = position.side_to_move;
 
-    let current_color = if is_white { WHITE } else { BLACK };
-    let opponent_color = if is_white { BLACK } else { WHITE };
+    // Direction constants based on color, in 64-square steps
+    let (forward, double_move_rank, promotion_rank) = match us {
+        Color::White => (-8, RANK_1 >> 8, RANK_8),
+        Color::Black => (8, RANK_8 << 8, RANK_1),
+    };
+    let occupancy = position.occupied();
+    let allowed = restrictions.allowed_targets(square);
 
     // Forward move
     let target_square = square.wrapping_add_signed(forward);
-    let target_piece = position.board[target_square];
-
-    if target_piece == EMPTY {
-        // Handle promotion
-        if get_rank(target_square) == promotion_rank {
-            for prom_piece in [QUEEN, KNIGHT, ROOK, BISHOP] {
-                moves.push(Move {
-                    from: square,
-                    to: target_square,
-                    promoted_piece: Some(current_color | prom_piece),
-                    is_capture: false,
-                    is_enpassant: false,
-                    is_double_pawn: false,
-                    is_castling: false,
-                });
+    if occupancy & square_bit(target_square) == 0 {
+        let target = square_bit(target_square) & allowed;
+        if square_bit(target_square) & promotion_rank != 0 {
+            if gen_type.includes_tactical() {
+                push_pawn_moves(square, target, false, promotion_rank, moves);
             }
-        } else if !only_tactical_moves {
-            // Normal forward move
-            moves.push(Move {
-                from: square,
-                to: target_square,
-                promoted_piece: None,
-                is_capture: false,
-                is_enpassant: false,
-                is_double_pawn: false,
-                is_castling: false,
-            });
+        } else if gen_type.includes_quiet() {
+            push_pawn_moves(square, target, false, promotion_rank, moves);
 
             // Double forward move from starting position
-            if get_rank(square) == rank_for_double_move {
-                let double_target = target_square.wrapping_add_signed(forward);
-                if position.board[double_target] == EMPTY {
-                    moves.push(Move {
-                        from: square,
-                        to: double_target,
-                        promoted_piece: None,
-                        is_capture: false,
-                        is_enpassant: false,
-                        is_double_pawn: true,
-                        is_castling: false,
-                    });
-                }
+            let double_target = target_square.wrapping_add_signed(forward);
+            if square_bit(square) & double_move_rank != 0
+                && occupancy & square_bit(double_target) == 0
+                && allowed & square_bit(double_target) != 0
+            {
+                moves.push(Move::new(
+                    Square::from_64(square),
+                    Square::from_64(double_target),
+                    DOUBLE_PAWN_FLAG,
+                ));
             }
         }
     }
 
+    if !gen_type.includes_tactical() {
+        return;
+    }
+
     // Diagonal captures
-    for diagonal in [forward + E, forward + W] {
-        let target_square = square.wrapping_add_signed(diagonal);
-        if is_off_board(target_square) {
-            continue;
+    let attacks = PAWN_ATTACKS[us.index()][square];
+    let captures = attacks & position.occupancy[(!us).index()] & allowed;
+    push_pawn_moves(square, captures, true, promotion_rank, moves);
+
+    if let Some(ep_square) = position.enpassant_square
+        && attacks & square_bit(ep_square.to_64()) != 0
+    {
+        let to = ep_square.to_64();
+        let captured = Square::from_file_rank(ep_square.file(), Square::from_64(square).rank());
+        let captured = captured.to_64();
+        // the captured pawn may be the checker even though its square is not the target
+        let resolves_check = restrictions.check_mask & (square_bit(to) | square_bit(captured));
+        if resolves_check != 0 && is_legal_enpassant(position, square, to, captured) {
+            moves.push(Move::new(
+                Square::from_64(square),
+                ep_square,
+                ENPASSANT_FLAG,
+            ))
         }
+    }
+}
 
-        let target_piece = position.board[target_square];
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenType {
+    All,
+    // captures, en passant and promotions
+    Tactical,
+    // everything else, including castling
+    Quiet,
+}
 
-        // Skip if same color piece
-        if get_piece_color(piece) == get_piece_color(target_piece) {
-            continue;
-        }
+impl GenType {
+    fn includes_tactical(self) -> bool {
+        self != GenType::Quiet
+    }
 
-        // Capture opponent's piece
-        if get_piece_color(target_piece) == opponent_color {
-            // Handle promotion
-            if get_rank(target_square) == promotion_rank {
-                for prom_piece in [QUEEN, KNIGHT, ROOK, BISHOP] {
-                    moves.push(Move {
-                        from: square,
-                        to: target_square,
-                        promoted_piece: Some(current_color | prom_piece),
-                        is_capture: true,
-                        is_enpassant: false,
-                        is_double_pawn: false,
-                        is_castling: false,
-                    });
+    fn includes_quiet(self) -> bool {
+        self != GenType::Tactical
+    }
+}
+
+// Generates only legal moves of the pieces in from_mask.
+fn generate_moves_from(
+    position: &Position,
+    restrictions: &Restrictions,
+    gen_type: GenType,
+    from_mask: u64,
+) -> MoveList {
+    let mut moves = MoveList::new();
+    let king_bit = square_bit(restrictions.king);
+
+    // in double check only the king can move
+    if restrictions.checkers.count_ones() < 2 {
+        let mut pieces = position.occupancy[position.side_to_move.index()] & from_mask;
+        while pieces != 0 {
+            let square = pop_lsb(&mut pieces);
+            let Some(piece) = position.piece_at(Square::from_64(square)) else {
+                continue;
+            };
+            match piece.kind() {
+                PieceKind::Pawn => {
+                    generate_pawn_moves(square, position, restrictions, &mut moves, gen_type)
+                }
+                PieceKind::King => {
+                    if gen_type.includes_quiet() && restrictions.checkers == 0 {
+                        generate_castling_moves(position, &mut moves);
+                    }
+                    generate_king_moves(position, restrictions, &mut moves, gen_type)
+                }
+                kind => {
+                    generate_piece_moves(square, kind, position, restrictions, &mut moves, gen_type)
                 }
-            } else {
-                moves.push(Move {
-                    from: square,
-                    to: target_square,
-                    promoted_piece: None,
-                    is_capture: true,
-                    is_enpassant: false,
-                    is_double_pawn: false,
-                    is_castling: false,
-                });
             }
         }
+    } else if from_mask & king_bit != 0 {
+        generate_king_moves(position, restrictions, &mut moves, gen_type);
+    }
 
-        let ep_square = position.enpassant_square.unwrap_or(127);
-        if target_square == ep_square {
-            moves.push(Move {
-                from: square,
-                to: target_square,
-                promoted_piece: None,
-                is_capture: true,
-                is_enpassant: true,
-                is_double_pawn: false,
-                is_castling: false,
-            })
-        }
+    moves
+}
+
+pub fn generate_moves(position: &Position, gen_type: GenType) -> MoveList {
+    generate_moves_from(position, &Restrictions::new(position), gen_type, u64::MAX)
+}
+
+// Moves out of check: king moves, captures of the checker and interpositions.
+// Only pieces that can reach the checker or a square in between are looked at,
+// and in double check just the king.
+pub fn generate_evasions(position: &Position) -> MoveList {
+    let restrictions = Restrictions::new(position);
+    debug_assert!(
+        restrictions.checkers != 0,
+        "evasions need a position in check"
+    );
+
+    let us = position.side_to_move;
+    let mask = restrictions.check_mask;
+    let mut candidates = square_bit(restrictions.king);
+    let mut targets = mask;
+    while targets != 0 {
+        let target = pop_lsb(&mut targets);
+        candidates |= attackers(position, target, position.occupied(), us);
+    }
+    // pawns push toward a8 for white and toward h1 for black
+    candidates |= match us {
+        Color::White => (mask << 8) | (mask << 16),
+        Color::Black => (mask >> 8) | (mask >> 16),
+    } & position.pieces(us, PieceKind::Pawn);
+    // en passant can take a checking pawn without landing on its square
+    if let Some(enpassant_square) = position.enpassant_square {
+        candidates |= PAWN_ATTACKS[(!us).index()][enpassant_square.to_64()]
+            & position.pieces(us, PieceKind::Pawn);
     }
+
+    generate_moves_from(position, &restrictions, GenType::All, candidates)
 }
 
-pub fn generate_pseudo_moves(position: &Position, only_tactical_moves: bool) -> Vec<Move> {
-    let mut moves: Vec<Move> = Vec::with_capacity(100);
+pub fn generate_legal_moves(position: &Position) -> MoveList {
+    generate_moves(position, GenType::All)
+}
 
-    for square in BOARD_SQUARES {
-        let piece = position.board[square];
-        if piece == EMPTY {
-            continue;
+// What it takes to check the enemy king: the squares each piece kind checks
+// from, and our pieces that uncover a slider's check by stepping off its line.
+struct CheckInfo {
+    king: usize,
+    check_squares: [u64; 7],
+    discoverers: u64,
+}
+
+impl CheckInfo {
+    fn new(position: &Position) -> Self {
+        let us = position.side_to_move;
+        let king = position.king_squares[(!us).index()].to_64();
+        let occupancy = position.occupied();
+        let bishop = bishop_attacks(king, occupancy);
+        let rook = rook_attacks(king, occupancy);
+
+        let mut check_squares = [0; 7];
+        check_squares[PieceKind::Pawn.index()] = PAWN_ATTACKS[(!us).index()][king];
+        check_squares[PieceKind::Knight.index()] = KNIGHT_ATTACKS[king];
+        check_squares[PieceKind::Bishop.index()] = bishop;
+        check_squares[PieceKind::Rook.index()] = rook;
+        check_squares[PieceKind::Queen.index()] = bishop | rook;
+
+        Self {
+            king,
+            check_squares,
+            discoverers: sole_blockers(position, king, us) & position.occupancy[us.index()],
         }
-        let side_to_move = if position.is_white_turn { WHITE } else { BLACK };
-        if get_piece_color(piece) != side_to_move {
-            continue;
+    }
+
+    fn gives_check(&self, position: &Position, move_: &Move) -> bool {
+        let us = position.side_to_move;
+        let (from, to) = (move_.from().to_64(), move_.to().to_64());
+        let king_bit = square_bit(self.king);
+        let queens = position.pieces(us, PieceKind::Queen);
+        let bishops = position.pieces(us, PieceKind::Bishop) | queens;
+        let rooks = position.pieces(us, PieceKind::Rook) | queens;
+
+        if move_.is_castling() {
+            // the rook checks from its new square, or the king uncovers a slider
+            let (king_to, rook_to) = get_castling_targets(move_);
+            let (king_to, rook_to) = (king_to.to_64(), rook_to.to_64());
+            let occupancy = (position.occupied() & !square_bit(from) & !square_bit(to))
+                | square_bit(king_to)
+                | square_bit(rook_to);
+            return rook_attacks(rook_to, occupancy) & king_bit != 0
+                || bishop_attacks(self.king, occupancy) & bishops != 0
+                || rook_attacks(self.king, occupancy) & rooks & !square_bit(to) != 0;
         }
-        match get_piece_type(piece) {
-            BISHOP | ROOK | QUEEN => {
-                generate_sliding_moves(square, position, &mut moves, only_tactical_moves)
-            }
-            KNIGHT | KING => {
-                generate_crawling_moves(square, position, &mut moves, only_tactical_moves)
-            }
-            PAWN => generate_pawn_moves(square, position, &mut moves, only_tactical_moves),
-            _ => continue,
+        if move_.is_enpassant() {
+            // taking the pawn can open a line as well
+            let captured = match us {
+                Color::White => to + 8,
+                Color::Black => to - 8,
+            };
+            let occupancy =
+                (position.occupied() ^ square_bit(from) ^ square_bit(captured)) | square_bit(to);
+            return self.check_squares[PieceKind::Pawn.index()] & square_bit(to) != 0
+                || bishop_attacks(self.king, occupancy) & bishops != 0
+                || rook_attacks(self.king, occupancy) & rooks != 0;
+        }
+
+        let discovered =
+            self.discoverers & square_bit(from) != 0 && line(from, self.king) & square_bit(to) == 0;
+        if let Some(promoted_piece) = move_.promoted_piece() {
+            // the pawn's square is empty behind the new piece
+            let occupancy = position.occupied() ^ square_bit(from);
+            return discovered || get_piece_attacks(promoted_piece, to, occupancy) & king_bit != 0;
+        }
+        let Some(piece) = position.piece_at(move_.from()) else {
+            return false;
+        };
+        discovered || self.check_squares[piece.kind().index()] & square_bit(to) != 0
+    }
+
+    // a check from one of our sliders other than the pieces that move, on a
+    // line opened by the move, or by the pawn taken en passant
+    fn discovers_check(&self, position: &Position, move_: &Move) -> bool {
+        let us = position.side_to_move;
+        let (from, to) = (move_.from().to_64(), move_.to().to_64());
+        let queens = position.pieces(us, PieceKind::Queen);
+        let bishops = position.pieces(us, PieceKind::Bishop) | queens;
+        let rooks = position.pieces(us, PieceKind::Rook) | queens;
+        let moved = square_bit(from) | square_bit(to);
+
+        let mut occupancy = (position.occupied() & !square_bit(from)) | square_bit(to);
+        if move_.is_castling() {
+            let (king_to, rook_to) = get_castling_targets(move_);
+            occupancy = (position.occupied() & !moved)
+                | square_bit(king_to.to_64())
+                | square_bit(rook_to.to_64());
+        } else if move_.is_enpassant() {
+            let captured = match us {
+                Color::White => to + 8,
+                Color::Black => to - 8,
+            };
+            occupancy ^= square_bit(captured);
         }
+        bishop_attacks(self.king, occupancy) & bishops & !moved != 0
+            || rook_attacks(self.king, occupancy) & rooks & !moved != 0
     }
+}
+
+// whether a legal move checks the enemy king, without making it
+pub fn gives_check(position: &Position, move_: &Move) -> bool {
+    CheckInfo::new(position).gives_check(position, move_)
+}
+
+// whether a legal move checks the enemy king with a piece other than the one
+// moved, true for double checks as well
+pub fn is_discovered_check(position: &Position, move_: &Move) -> bool {
+    CheckInfo::new(position).discovers_check(position, move_)
+}
 
+// quiet moves that give check, directly or by uncovering a slider
+pub fn generate_quiet_checks(position: &Position) -> MoveList {
+    let check_info = CheckInfo::new(position);
+    let mut moves = generate_moves(position, GenType::Quiet);
+    moves.retain(|move_| check_info.gives_check(position, move_));
     moves
 }
 
-pub fn generate_legal_moves(position: &mut Position) -> Vec<Move> {
-    // TODO: Optimize move-lists
-    // Currently creates new vectors in pseudo_moves and again in legal_moves
-    // Maybe generate in pseudo moves and swap_remove in legal moves
-    // I could use while-loop and swap_remove illegal moves after unmake
-    let pseudo_moves = generate_pseudo_moves(position, false);
-    let mut legal_moves: Vec<Move> = Vec::with_capacity(100);
-    for move_ in &pseudo_moves {
-        position.make_move(move_, 0); // make move
-        position.is_white_turn = !position.is_white_turn; // consider from same side before move
-        let idx = match position.is_white_turn {
-            true => 0,
-            false => 1,
-        };
-        if !is_square_attacked(position.king_squares[idx], position) {
-            legal_moves.push(*move_); // TODO DEBUG
+// checks a move from elsewhere, like the TT or a killer slot, against the current position
+pub fn is_legal_move(position: &Position, move_: &Move) -> bool {
+    let from = move_.from().to_64();
+    position.occupancy[position.side_to_move.index()] & square_bit(from) != 0
+        && generate_moves_from(
+            position,
+            &Restrictions::new(position),
+            GenType::All,
+            square_bit(from),
+        )
+        .contains(move_)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::START_POSITION_FEN;
+
+    #[test]
+    fn test_move_encoding_round_trip() {
+        let fens = [
+            START_POSITION_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ];
+        for fen in fens {
+            let position = Position::from_fen(fen);
+            for move_ in &generate_legal_moves(&position) {
+                let decoded = move_.decode();
+                assert_eq!(decoded.encode(), move_);
+                assert_eq!(decoded.from, move_.from());
+                assert_eq!(decoded.is_capture, move_.is_capture());
+            }
         }
-        position.is_white_turn = !position.is_white_turn;
-        position.unmake_move(move_, 0);
+        assert_eq!(std::mem::size_of::<Move>(), 2);
     }
-    legal_moves
 }
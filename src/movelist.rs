@@ -0,0 +1,137 @@
+use std::ops::Index;
+
+use crate::movegen::Move;
+
+// no legal chess position has more than 218 moves
+pub const MAX_MOVES: usize = 256;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScoredMove {
+    pub move_: Move,
+    pub score: i32,
+}
+
+// Fixed-capacity move list kept on the stack, so generating moves doesn't allocate.
+#[derive(Clone)]
+pub struct MoveList {
+    entries: [ScoredMove; MAX_MOVES],
+    len: usize,
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        Self {
+            entries: [ScoredMove::default(); MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, move_: Move) {
+        self.entries[self.len] = ScoredMove { move_, score: 0 };
+        self.len += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Move> + '_ {
+        self.entries[..self.len].iter().map(|entry| entry.move_)
+    }
+
+    pub fn entries(&self) -> &[ScoredMove] {
+        &self.entries[..self.len]
+    }
+
+    pub fn entries_mut(&mut self) -> &mut [ScoredMove] {
+        &mut self.entries[..self.len]
+    }
+
+    pub fn contains(&self, move_: &Move) -> bool {
+        self.iter().any(|other| other == *move_)
+    }
+
+    // keeps the moves for which keep returns true, in their original order
+    pub fn retain(&mut self, mut keep: impl FnMut(&Move) -> bool) {
+        let mut kept = 0;
+        for i in 0..self.len {
+            if keep(&self.entries[i].move_) {
+                self.entries[kept] = self.entries[i];
+                kept += 1;
+            }
+        }
+        self.len = kept;
+    }
+
+    // stable insertion sort by ascending score, lists are short and mostly small
+    pub fn sort_by_score(&mut self) {
+        let entries = self.entries_mut();
+        for i in 1..entries.len() {
+            let entry = entries[i];
+            let mut j = i;
+            while j > 0 && entries[j - 1].score > entry.score {
+                entries[j] = entries[j - 1];
+                j -= 1;
+            }
+            entries[j] = entry;
+        }
+    }
+}
+
+impl Index<usize> for MoveList {
+    type Output = Move;
+
+    fn index(&self, index: usize) -> &Move {
+        &self.entries()[index].move_
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = Move;
+    type IntoIter = std::iter::Map<std::slice::Iter<'a, ScoredMove>, fn(&ScoredMove) -> Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries().iter().map(|entry| entry.move_)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{START_POSITION_FEN, position::Position};
+
+    #[test]
+    fn test_sort_and_retain_keep_order() {
+        let position = Position::from_fen(START_POSITION_FEN);
+        let mut moves = position.generate_legal_moves();
+        assert_eq!(moves.len(), 20);
+        let original: Vec<Move> = moves.iter().collect();
+
+        // equal scores keep generation order
+        for (i, entry) in moves.entries_mut().iter_mut().enumerate() {
+            entry.score = if i % 2 == 0 { 1 } else { 0 };
+        }
+        moves.sort_by_score();
+        let odd = original.iter().skip(1).step_by(2);
+        let even = original.iter().step_by(2);
+        assert!(moves.iter().eq(odd.chain(even).copied()));
+
+        moves.retain(|move_| move_.is_double_pawn());
+        assert_eq!(moves.len(), 8);
+        assert!(moves.iter().all(|move_| original.contains(&move_)));
+    }
+}
@@ -0,0 +1,343 @@
+use crate::{
+    evaluation::get_material_score,
+    movegen::{
+        GenType, Move, generate_evasions, generate_moves, generate_quiet_checks, is_legal_move,
+    },
+    movelist::{MoveList, ScoredMove},
+    moveordering::capture_score,
+    position::Position,
+    see::{see, see_ge},
+};
+
+// tactical moves are scored into bands, so picking the best one walks the stages in order
+const GOOD_CAPTURE_SCORE: i32 = 20000;
+const PROMOTION_SCORE: i32 = 10000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    TtMove,
+    GenerateTactical,
+    GoodCaptures,
+    Promotions,
+    Killers,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    GenerateQuietChecks,
+    QuietChecks,
+    GenerateEvasions,
+    Evasions,
+    Done,
+}
+
+// Hands out legal moves one at a time in search order: TT move, good captures,
+// quiet promotions, killers, quiets by history and bad captures last, those
+// losing material by SEE and ordered by how much they lose.
+// Each stage is only generated once the previous ones are used up, so a cutoff
+// by the TT move doesn't generate anything. In check there is a single stage of
+// evasions instead, tactical moves first and quiets by history.
+pub struct MovePicker {
+    stage: Stage,
+    tt_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    killer_index: usize,
+    captures_only: bool,
+    quiet_checks: bool,
+    in_check: bool,
+    tactical: MoveList,
+    tactical_index: usize,
+    quiets: MoveList,
+    quiet_index: usize,
+}
+
+impl MovePicker {
+    pub fn new(tt_move: Option<Move>, killers: [Option<Move>; 2]) -> Self {
+        Self {
+            stage: Stage::TtMove,
+            tt_move,
+            killers,
+            killer_index: 0,
+            captures_only: false,
+            quiet_checks: false,
+            in_check: false,
+            tactical: MoveList::new(),
+            tactical_index: 0,
+            quiets: MoveList::new(),
+            quiet_index: 0,
+        }
+    }
+
+    // captures and promotions only, for quiescence, where losing captures are pruned
+    pub fn new_captures() -> Self {
+        Self {
+            stage: Stage::GenerateTactical,
+            captures_only: true,
+            ..Self::new(None, [None; 2])
+        }
+    }
+
+    // captures and promotions followed by quiet checks, for the first ply of quiescence
+    pub fn new_captures_and_checks() -> Self {
+        Self {
+            quiet_checks: true,
+            ..Self::new_captures()
+        }
+    }
+
+    // every evasion, for positions where the side to move is in check
+    pub fn new_evasions(tt_move: Option<Move>) -> Self {
+        Self {
+            in_check: true,
+            ..Self::new(tt_move, [None; 2])
+        }
+    }
+
+    pub fn next(&mut self, position: &Position, history: &[[u32; 128]; 128]) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = if self.in_check {
+                        Stage::GenerateEvasions
+                    } else {
+                        Stage::GenerateTactical
+                    };
+                    if let Some(tt_move) = self.tt_move {
+                        if is_legal_move(position, &tt_move) {
+                            return Some(tt_move);
+                        }
+                        self.tt_move = None;
+                    }
+                }
+                Stage::GenerateTactical => {
+                    self.tactical = generate_moves(position, GenType::Tactical);
+                    for entry in self.tactical.entries_mut() {
+                        entry.score = score_tactical(position, &entry.move_);
+                    }
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures | Stage::Promotions => {
+                    let min_score = if self.stage == Stage::GoodCaptures {
+                        GOOD_CAPTURE_SCORE
+                    } else {
+                        PROMOTION_SCORE
+                    };
+                    match self.pick_tactical(min_score) {
+                        Some(move_) => return Some(move_),
+                        None if self.stage == Stage::GoodCaptures => self.stage = Stage::Promotions,
+                        None if self.quiet_checks => self.stage = Stage::GenerateQuietChecks,
+                        None if self.captures_only => self.stage = Stage::Done,
+                        None => self.stage = Stage::Killers,
+                    }
+                }
+                Stage::Killers => {
+                    if self.killer_index == self.killers.len() {
+                        self.stage = Stage::GenerateQuiets;
+                        continue;
+                    }
+                    let killer = self.killers[self.killer_index];
+                    self.killer_index += 1;
+                    // tactical killers were already handed out with the captures
+                    if let Some(killer) = killer
+                        && !killer.is_tactical()
+                        && Some(killer) != self.tt_move
+                        && (self.killer_index == 1 || self.killers[0] != self.killers[1])
+                        && is_legal_move(position, &killer)
+                    {
+                        return Some(killer);
+                    }
+                }
+                Stage::GenerateQuiets => {
+                    self.quiets = generate_moves(position, GenType::Quiet);
+                    for entry in self.quiets.entries_mut() {
+                        let (from, to) = (entry.move_.from().index(), entry.move_.to().index());
+                        entry.score = history[from][to] as i32;
+                    }
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    while let Some(entry) = pick_best(&mut self.quiets, self.quiet_index) {
+                        self.quiet_index += 1;
+                        let move_ = Some(entry.move_);
+                        if move_ != self.tt_move && !self.killers.contains(&move_) {
+                            return move_;
+                        }
+                    }
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::BadCaptures => match self.pick_tactical(i32::MIN) {
+                    Some(move_) => return Some(move_),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::GenerateQuietChecks => {
+                    // checks that just hang the piece are pruned like losing captures
+                    self.quiets = generate_quiet_checks(position);
+                    self.quiets.retain(|move_| see_ge(position, *move_, 0));
+                    for entry in self.quiets.entries_mut() {
+                        let (from, to) = (entry.move_.from().index(), entry.move_.to().index());
+                        entry.score = history[from][to] as i32;
+                    }
+                    self.stage = Stage::QuietChecks;
+                }
+                Stage::QuietChecks => match pick_best(&mut self.quiets, self.quiet_index) {
+                    Some(entry) => {
+                        self.quiet_index += 1;
+                        return Some(entry.move_);
+                    }
+                    None => self.stage = Stage::Done,
+                },
+                Stage::GenerateEvasions => {
+                    self.tactical = generate_evasions(position);
+                    for entry in self.tactical.entries_mut() {
+                        let move_ = entry.move_;
+                        entry.score = if move_.is_tactical() {
+                            // above any history score
+                            score_tactical(position, &move_) + i32::MAX / 2
+                        } else {
+                            history[move_.from().index()][move_.to().index()] as i32
+                        };
+                    }
+                    self.stage = Stage::Evasions;
+                }
+                Stage::Evasions => match self.pick_tactical(i32::MIN) {
+                    Some(move_) => return Some(move_),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    // best remaining tactical move scoring at least min_score, skipping the TT move
+    fn pick_tactical(&mut self, min_score: i32) -> Option<Move> {
+        while let Some(entry) = pick_best(&mut self.tactical, self.tactical_index) {
+            if entry.score < min_score {
+                return None;
+            }
+            self.tactical_index += 1;
+            if Some(entry.move_) != self.tt_move {
+                return Some(entry.move_);
+            }
+        }
+        None
+    }
+}
+
+fn score_tactical(position: &Position, move_: &Move) -> i32 {
+    if !move_.is_capture() {
+        let promoted_piece = move_
+            .promoted_piece()
+            .expect("quiet tactical move promotes");
+        return PROMOTION_SCORE + get_material_score(promoted_piece);
+    }
+    // good captures by MVV-LVA, the losing ones by how much they lose
+    let see_value = see(position, *move_);
+    if see_value >= 0 {
+        GOOD_CAPTURE_SCORE + capture_score(position, move_)
+    } else {
+        see_value
+    }
+}
+
+// moves the best scored entry from index onwards to index, first one wins ties
+fn pick_best(moves: &mut MoveList, index: usize) -> Option<ScoredMove> {
+    let entries = moves.entries_mut();
+    if index >= entries.len() {
+        return None;
+    }
+    let mut best = index;
+    for i in index + 1..entries.len() {
+        if entries[i].score > entries[best].score {
+            best = i;
+        }
+    }
+    entries.swap(index, best);
+    Some(entries[index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picked_moves(mut picker: MovePicker, position: &Position) -> Vec<Move> {
+        let history = [[0u32; 128]; 128];
+        let mut moves = Vec::new();
+        while let Some(move_) = picker.next(position, &history) {
+            moves.push(move_);
+        }
+        moves
+    }
+
+    #[test]
+    fn test_picker_yields_every_legal_move_once() {
+        let position =
+            Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        let legal_moves = position.generate_legal_moves();
+        let quiet = legal_moves.iter().find(|move_| !move_.is_capture());
+        let capture = legal_moves.iter().find(|move_| move_.is_capture());
+
+        let picked = picked_moves(MovePicker::new(quiet, [capture, quiet]), &position);
+        assert_eq!(picked.len(), legal_moves.len());
+        assert!(legal_moves.iter().all(|move_| picked.contains(&move_)));
+        assert_eq!(picked.first().copied(), quiet);
+
+        // losing captures are left out of quiescence
+        let picked = picked_moves(MovePicker::new_captures(), &position);
+        let mut tactical_moves = position.generate_tactical_moves();
+        tactical_moves.retain(|move_| see_ge(&position, *move_, 0));
+        assert_eq!(picked.len(), tactical_moves.len());
+        assert!(picked.iter().all(|move_| move_.is_capture()));
+
+        let picked = picked_moves(MovePicker::new_captures_and_checks(), &position);
+        let mut quiet_checks = position.generate_quiet_checks();
+        quiet_checks.retain(|move_| see_ge(&position, *move_, 0));
+        assert_eq!(picked.len(), tactical_moves.len() + quiet_checks.len());
+        assert!(
+            picked[tactical_moves.len()..]
+                .iter()
+                .all(|move_| quiet_checks.contains(move_))
+        );
+    }
+
+    #[test]
+    fn test_picker_yields_duplicate_killers_once() {
+        let position = Position::from_fen(crate::START_POSITION_FEN);
+        let legal_moves = position.generate_legal_moves();
+        let (first, second) = (legal_moves[0], legal_moves[1]);
+
+        for (tt_move, killers) in [
+            (None, [Some(first), Some(first)]),
+            (Some(first), [Some(first), Some(second)]),
+            (Some(second), [Some(first), Some(second)]),
+        ] {
+            let picked = picked_moves(MovePicker::new(tt_move, killers), &position);
+            assert_eq!(picked.len(), legal_moves.len());
+            assert_eq!(picked.iter().filter(|&&move_| move_ == first).count(), 1);
+            assert_eq!(picked.iter().filter(|&&move_| move_ == second).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_picker_yields_evasions_captures_first() {
+        // the rook checks from e2, it can be taken by the king, the knight or the bishop
+        let position = Position::from_fen("4k3/8/8/8/8/2N5/4r3/3BK3 w - - 0 1");
+        let legal_moves = position.generate_legal_moves();
+        let picked = picked_moves(MovePicker::new_evasions(None), &position);
+        assert_eq!(picked.len(), legal_moves.len());
+        assert!(legal_moves.iter().all(|move_| picked.contains(&move_)));
+        assert!(picked[..3].iter().all(|move_| move_.is_capture()));
+        assert!(picked[3..].iter().all(|move_| !move_.is_capture()));
+    }
+
+    #[test]
+    fn test_picker_skips_illegal_tt_move_and_orders_captures() {
+        // pawn takes knight first, the queen can take the loose pawn on e6 but
+        // the other queen captures lose material and go last, the worst one last
+        let position = Position::from_fen("4k3/8/2n1p3/1P1r1p2/4Q3/8/8/4K3 w - - 0 1");
+        let illegal = Position::from_fen(crate::START_POSITION_FEN).generate_legal_moves()[0];
+        let picked = picked_moves(MovePicker::new(Some(illegal), [None; 2]), &position);
+        let strings: Vec<String> = picked.iter().map(crate::movegen::get_move_string).collect();
+        assert_eq!(strings[..2], ["b5c6", "e4e6"]);
+        assert_eq!(strings[strings.len() - 2..], ["e4d5", "e4f5"]);
+        assert!(!picked.contains(&illegal));
+    }
+}
@@ -0,0 +1,709 @@
+use std::{
+    io::{BufRead, Write},
+    time::Duration,
+};
+
+use crate::{
+    START_POSITION_FEN,
+    movegen::Move,
+    piece::Color,
+    position::{FenError, Outcome, Position},
+    san::MoveParseError,
+};
+
+// export format line length
+const LINE_WIDTH: usize = 80;
+
+// tags every PGN game is supposed to have, in export order
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    Unknown,
+}
+
+impl GameResult {
+    pub fn parse(s: &str) -> Option<GameResult> {
+        match s {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unknown),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PgnError {
+    Io(std::io::Error),
+    InvalidTag(String),
+    InvalidFen(FenError),
+    InvalidMove(MoveParseError),
+    UnexpectedToken(String),
+    UnterminatedComment,
+    UnterminatedVariation,
+}
+
+impl std::fmt::Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PgnError::Io(error) => write!(f, "read error: {}", error),
+            PgnError::InvalidTag(s) => write!(f, "invalid tag '{}'", s),
+            PgnError::InvalidFen(error) => write!(f, "invalid FEN tag: {}", error),
+            PgnError::InvalidMove(error) => write!(f, "{}", error),
+            PgnError::UnexpectedToken(s) => write!(f, "unexpected '{}'", s),
+            PgnError::UnterminatedComment => write!(f, "comment is not closed"),
+            PgnError::UnterminatedVariation => write!(f, "variation is not closed"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnMove {
+    pub move_: Move,
+    // numeric annotation glyphs, with ! ? !! ?? !? ?! stored as $1 to $6
+    pub nags: Vec<u8>,
+    // comment in front of the first move of a game or variation
+    pub comment_before: Option<String>,
+    pub comment: Option<String>,
+    // alternatives to this move, each played from the position before it
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+#[derive(Clone)]
+pub struct Game {
+    // in file order
+    pub tags: Vec<(String, String)>,
+    pub start: Position,
+    pub moves: Vec<PgnMove>,
+    pub result: GameResult,
+}
+
+impl PgnMove {
+    pub fn new(move_: Move) -> Self {
+        Self {
+            move_,
+            nags: Vec::new(),
+            comment_before: None,
+            comment: None,
+            variations: Vec::new(),
+        }
+    }
+
+    // moves without annotations, such as a PV to add as a variation
+    pub fn line(moves: &[Move]) -> Vec<PgnMove> {
+        moves.iter().map(|&move_| PgnMove::new(move_)).collect()
+    }
+}
+
+// {+0.35/12 1.2s}, the score in pawns for the side that played the move
+pub fn engine_comment(score: i32, depth: u32, time: Duration) -> String {
+    format!(
+        "{:+.2}/{} {:.1}s",
+        score as f64 / 100.0,
+        depth,
+        time.as_secs_f64()
+    )
+}
+
+impl Game {
+    pub fn new(start: Position) -> Self {
+        Self {
+            tags: Vec::new(),
+            start,
+            moves: Vec::new(),
+            result: GameResult::Unknown,
+        }
+    }
+
+    // a game from the moves played since start, the result is set if the
+    // last position ends the game
+    pub fn from_moves(start: Position, moves: &[Move]) -> Self {
+        let mut game = Game::new(start);
+        game.moves = PgnMove::line(moves);
+
+        // outcome needs the earlier positions to count repetitions
+        let mut position = start;
+        let mut hash_history = Vec::with_capacity(moves.len());
+        for move_ in moves {
+            hash_history.push(position.hash);
+            position.make_move(move_);
+        }
+        game.result = match position.outcome(&hash_history) {
+            Some(Outcome {
+                winner: Some(Color::White),
+                ..
+            }) => GameResult::WhiteWins,
+            Some(Outcome {
+                winner: Some(Color::Black),
+                ..
+            }) => GameResult::BlackWins,
+            Some(_) => GameResult::Draw,
+            None => GameResult::Unknown,
+        };
+        game
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn mainline(&self) -> impl Iterator<Item = Move> + '_ {
+        self.moves.iter().map(|pgn_move| pgn_move.move_)
+    }
+
+    // position after the mainline
+    pub fn end(&self) -> Position {
+        let mut position = self.start;
+        for move_ in self.mainline() {
+            position.make_move(&move_);
+        }
+        position
+    }
+
+    // export format: the seven tag roster first, then any other tags, and the
+    // movetext in SAN wrapped at 80 columns
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => self.result.as_str(),
+                "Date" => self.tag(name).unwrap_or("????.??.??"),
+                _ => self.tag(name).unwrap_or("?"),
+            };
+            push_tag(&mut pgn, name, value);
+        }
+        let fen = self.start.to_fen();
+        if fen != START_POSITION_FEN && self.tag("FEN").is_none() {
+            if self.start.chess960 && self.tag("Variant").is_none() {
+                push_tag(&mut pgn, "Variant", "Chess960");
+            }
+            push_tag(&mut pgn, "SetUp", "1");
+            push_tag(&mut pgn, "FEN", &fen);
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) {
+                push_tag(&mut pgn, name, value);
+            }
+        }
+        pgn.push('\n');
+
+        let mut tokens = Vec::new();
+        push_line(&mut tokens, &self.start, &self.moves);
+        tokens.push(self.result.as_str().to_string());
+        let mut width = 0;
+        for token in tokens {
+            if width > 0 && width + 1 + token.len() > LINE_WIDTH {
+                pgn.push('\n');
+                width = 0;
+            } else if width > 0 {
+                pgn.push(' ');
+                width += 1;
+            }
+            pgn.push_str(&token);
+            width += token.len();
+        }
+        pgn.push_str("\n\n");
+        pgn
+    }
+
+    pub fn write_pgn<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(self.to_pgn().as_bytes())
+    }
+}
+
+fn push_tag(pgn: &mut String, name: &str, value: &str) {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    pgn.push_str(&format!("[{name} \"{value}\"]\n"));
+}
+
+// a comment as separate words, so that long ones wrap like the moves do
+fn push_comment(tokens: &mut Vec<String>, comment: &str) {
+    let comment = comment.replace('}', ")");
+    let mut words: Vec<String> = comment.split_whitespace().map(str::to_string).collect();
+    if words.is_empty() {
+        words.push(String::new());
+    }
+    words[0].insert(0, '{');
+    words.last_mut().unwrap().push('}');
+    tokens.extend(words);
+}
+
+// move numbers go before white moves, and before black ones at the start of a
+// line or after a comment or variation
+fn push_line(tokens: &mut Vec<String>, start: &Position, moves: &[PgnMove]) {
+    let mut position = *start;
+    let mut number_black = true;
+    for pgn_move in moves {
+        if let Some(comment) = &pgn_move.comment_before {
+            push_comment(tokens, comment);
+        }
+        let number = position.fullmove_number;
+        match position.side_to_move {
+            Color::White => tokens.push(format!("{number}.")),
+            Color::Black if number_black => tokens.push(format!("{number}...")),
+            Color::Black => {}
+        }
+        tokens.push(position.move_to_san(&pgn_move.move_));
+        tokens.extend(pgn_move.nags.iter().map(|nag| format!("${nag}")));
+        number_black = false;
+
+        if let Some(comment) = &pgn_move.comment {
+            push_comment(tokens, comment);
+            number_black = true;
+        }
+        for variation in &pgn_move.variations {
+            let mut variation_tokens = Vec::new();
+            push_line(&mut variation_tokens, &position, variation);
+            if let Some(first) = variation_tokens.first_mut() {
+                first.insert(0, '(');
+                variation_tokens.last_mut().unwrap().push(')');
+                tokens.extend(variation_tokens);
+                number_black = true;
+            }
+        }
+        position.make_move(&pgn_move.move_);
+    }
+}
+
+// Reads games one at a time from any buffered reader, so only the game being
+// parsed is ever held in memory. A broken game is returned as an error and
+// reading goes on with the next one.
+pub struct PgnReader<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    // a tag line that ended a game without a result, it starts the next one
+    pending: Option<String>,
+    done: bool,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            pending: None,
+            done: false,
+        }
+    }
+
+    fn read_line(&mut self) -> std::io::Result<Option<String>> {
+        if let Some(line) = self.pending.take() {
+            return Ok(Some(line));
+        }
+        self.buffer.clear();
+        if self.reader.read_until(b'\n', &mut self.buffer)? == 0 {
+            return Ok(None);
+        }
+        // old PGN files are often Latin-1, the odd broken name is not worth failing on
+        Ok(Some(String::from_utf8_lossy(&self.buffer).into_owned()))
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<Game, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut builder = GameBuilder::default();
+        loop {
+            let line = match self.read_line() {
+                Ok(Some(line)) => line,
+                Ok(None) => {
+                    self.done = true;
+                    return (builder.in_movetext || !builder.tags.is_empty())
+                        .then(|| builder.finish());
+                }
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(PgnError::Io(error)));
+                }
+            };
+            let text = line.trim_start_matches('\u{feff}').trim();
+
+            if builder.comment.is_none() {
+                if text.starts_with('[') {
+                    if builder.in_movetext {
+                        self.pending = Some(line);
+                        return Some(builder.finish());
+                    }
+                    builder.add_tags(text);
+                    continue;
+                }
+                // escaped line
+                if text.starts_with('%') {
+                    continue;
+                }
+            }
+            if builder.add_movetext(text) {
+                return Some(builder.finish());
+            }
+        }
+    }
+}
+
+// a line of moves being read, the mainline or a variation
+struct Line {
+    moves: Vec<PgnMove>,
+    position: Position,
+    // position before the last move, where a variation of it starts
+    previous: Position,
+    comment_before: Option<String>,
+}
+
+impl Line {
+    fn new(position: Position) -> Self {
+        Self {
+            moves: Vec::new(),
+            position,
+            previous: position,
+            comment_before: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct GameBuilder {
+    tags: Vec<(String, String)>,
+    start: Option<Position>,
+    lines: Vec<Line>,
+    // text of a {} comment that is still open at the end of a line
+    comment: Option<String>,
+    depth: usize,
+    in_movetext: bool,
+    result: Option<GameResult>,
+    // only the first error is kept, the rest of the game is just skipped over
+    error: Option<PgnError>,
+}
+
+impl GameBuilder {
+    fn fail(&mut self, error: PgnError) {
+        self.error.get_or_insert(error);
+    }
+
+    // [Event "F/S Return Match"], a line may hold more than one
+    fn add_tags(&mut self, mut text: &str) {
+        while let Some(rest) = text.strip_prefix('[') {
+            match parse_tag(rest) {
+                Some((name, value, rest)) => {
+                    self.tags.push((name, value));
+                    text = rest.trim_start();
+                }
+                None => {
+                    self.fail(PgnError::InvalidTag(text.to_string()));
+                    return;
+                }
+            }
+        }
+    }
+
+    // the main line, set up from the FEN tag once the moves start
+    fn line(&mut self) -> Result<&mut Line, PgnError> {
+        if self.lines.is_empty() {
+            let position = self.start_position()?;
+            self.start = Some(position);
+            self.lines.push(Line::new(position));
+        }
+        Ok(self.lines.last_mut().unwrap())
+    }
+
+    fn start_position(&self) -> Result<Position, PgnError> {
+        let fen = self
+            .tags
+            .iter()
+            .find(|(name, _)| name == "FEN")
+            .map_or(START_POSITION_FEN, |(_, fen)| fen.as_str());
+        let mut position = Position::try_from_fen(fen).map_err(PgnError::InvalidFen)?;
+        position.chess960 = self
+            .tags
+            .iter()
+            .any(|(name, value)| name == "Variant" && value.to_lowercase().contains("960"));
+        Ok(position)
+    }
+
+    // feeds one line of movetext, returns true once the game result is read
+    fn add_movetext(&mut self, mut text: &str) -> bool {
+        self.in_movetext |= !text.is_empty();
+        loop {
+            if let Some(comment) = &mut self.comment {
+                let Some(end) = text.find('}') else {
+                    comment.push_str(text);
+                    comment.push(' ');
+                    return false;
+                };
+                comment.push_str(&text[..end]);
+                let comment = self.comment.take().unwrap();
+                self.add_comment(comment.trim());
+                text = &text[end + 1..];
+            }
+
+            text = text.trim_start();
+            let Some(c) = text.chars().next() else {
+                return false;
+            };
+            match c {
+                '{' => {
+                    self.comment = Some(String::new());
+                    text = &text[1..];
+                }
+                // rest of line comment
+                ';' => {
+                    self.add_comment(text[1..].trim());
+                    return false;
+                }
+                '(' => {
+                    self.depth += 1;
+                    self.start_variation();
+                    text = &text[1..];
+                }
+                ')' => {
+                    if self.depth == 0 {
+                        self.fail(PgnError::UnexpectedToken(")".to_string()));
+                    } else {
+                        self.depth -= 1;
+                        self.end_variation();
+                    }
+                    text = &text[1..];
+                }
+                _ => {
+                    let end = text
+                        .find(|c: char| c.is_whitespace() || "{}();".contains(c))
+                        .unwrap_or(text.len());
+                    if end == 0 {
+                        self.fail(PgnError::UnexpectedToken(c.to_string()));
+                        text = &text[c.len_utf8()..];
+                        continue;
+                    }
+                    if self.add_symbol(&text[..end]) {
+                        return true;
+                    }
+                    text = &text[end..];
+                }
+            }
+        }
+    }
+
+    fn add_symbol(&mut self, token: &str) -> bool {
+        if let Some(result) = GameResult::parse(token) {
+            if self.depth > 0 {
+                self.fail(PgnError::UnterminatedVariation);
+            }
+            self.result = Some(result);
+            return true;
+        }
+
+        if let Some(nag) = token.strip_prefix('$') {
+            match nag.parse() {
+                Ok(nag) => self.add_nag(nag),
+                Err(_) => self.fail(PgnError::UnexpectedToken(token.to_string())),
+            }
+            return false;
+        }
+
+        // move numbers, 12. or 12... and sometimes glued to the move
+        let digits = token
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(token.len());
+        let mut token = token;
+        if digits == token.len() || token[digits..].starts_with('.') {
+            token = &token[digits..];
+        }
+        let token = token.trim_start_matches('.');
+        if token.is_empty() || token == "e.p." {
+            return false;
+        }
+
+        let san = token.trim_end_matches(['!', '?']);
+        let nag = suffix_nag(&token[san.len()..]);
+        if !san.is_empty() {
+            self.add_move(san);
+        }
+        if let Some(nag) = nag {
+            self.add_nag(nag);
+        }
+        false
+    }
+
+    fn add_move(&mut self, san: &str) {
+        if self.error.is_some() {
+            return;
+        }
+        let line = match self.line() {
+            Ok(line) => line,
+            Err(error) => return self.fail(error),
+        };
+        let move_ = match line.position.parse_san(san) {
+            Ok(move_) => move_,
+            Err(error) => return self.fail(PgnError::InvalidMove(error)),
+        };
+        line.previous = line.position;
+        line.position.make_move(&move_);
+        let comment_before = line.comment_before.take();
+        line.moves.push(PgnMove {
+            move_,
+            nags: Vec::new(),
+            comment_before,
+            comment: None,
+            variations: Vec::new(),
+        });
+    }
+
+    fn add_nag(&mut self, nag: u8) {
+        if self.error.is_some() {
+            return;
+        }
+        match self.lines.last_mut().and_then(|line| line.moves.last_mut()) {
+            Some(pgn_move) => pgn_move.nags.push(nag),
+            None => self.fail(PgnError::UnexpectedToken(format!("${nag}"))),
+        }
+    }
+
+    fn add_comment(&mut self, comment: &str) {
+        if self.error.is_some() || comment.is_empty() {
+            return;
+        }
+        let line = match self.line() {
+            Ok(line) => line,
+            Err(error) => return self.fail(error),
+        };
+        let text = match line.moves.last_mut() {
+            Some(pgn_move) => &mut pgn_move.comment,
+            None => &mut line.comment_before,
+        };
+        match text {
+            Some(text) => {
+                text.push(' ');
+                text.push_str(comment);
+            }
+            None => *text = Some(comment.to_string()),
+        }
+    }
+
+    fn start_variation(&mut self) {
+        if self.error.is_some() {
+            return;
+        }
+        match self.lines.last() {
+            Some(line) if !line.moves.is_empty() => {
+                let line = Line::new(line.previous);
+                self.lines.push(line);
+            }
+            _ => self.fail(PgnError::UnexpectedToken("(".to_string())),
+        }
+    }
+
+    fn end_variation(&mut self) {
+        if self.error.is_some() {
+            return;
+        }
+        let variation = self.lines.pop().unwrap();
+        let parent = self.lines.last_mut().unwrap();
+        if !variation.moves.is_empty() {
+            parent
+                .moves
+                .last_mut()
+                .unwrap()
+                .variations
+                .push(variation.moves);
+        }
+    }
+
+    fn finish(mut self) -> Result<Game, PgnError> {
+        if self.comment.is_some() {
+            self.fail(PgnError::UnterminatedComment);
+        } else if self.depth > 0 {
+            self.fail(PgnError::UnterminatedVariation);
+        }
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let result = self.result.unwrap_or_else(|| {
+            self.tags
+                .iter()
+                .find(|(name, _)| name == "Result")
+                .and_then(|(_, value)| GameResult::parse(value))
+                .unwrap_or(GameResult::Unknown)
+        });
+        let start = match self.start {
+            Some(start) => start,
+            None => self.start_position()?,
+        };
+        let moves = self
+            .lines
+            .into_iter()
+            .next()
+            .map_or(Vec::new(), |line| line.moves);
+        Ok(Game {
+            tags: self.tags,
+            start,
+            moves,
+            result,
+        })
+    }
+}
+
+// name, value and the text after the closing bracket of a tag, from just
+// after its opening bracket
+fn parse_tag(text: &str) -> Option<(String, String, &str)> {
+    let text = text.trim_start();
+    let name_end = text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))?;
+    if name_end == 0 {
+        return None;
+    }
+    let quoted = text[name_end..].trim_start().strip_prefix('"')?;
+    let mut value = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => value.extend(chars.next().map(|(_, c)| c)),
+            '"' => {
+                let rest = quoted[index + 1..].trim_start().strip_prefix(']')?;
+                return Some((text[..name_end].to_string(), value, rest));
+            }
+            _ => value.push(c),
+        }
+    }
+    None
+}
+
+// ! ? !! ?? !? ?! as their NAG numbers
+fn suffix_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
@@ -1,46 +1,154 @@
-pub const EMPTY: u8 = 0b00000;
-pub const PAWN: u8 = 0b00001;
-pub const KNIGHT: u8 = 0b00010;
-pub const BISHOP: u8 = 0b00011;
-pub const ROOK: u8 = 0b00100;
-pub const QUEEN: u8 = 0b00101;
-pub const KING: u8 = 0b00110;
-pub const WHITE: u8 = 0b01000;
-pub const BLACK: u8 = 0b10000;
-
-pub fn get_piece_color(piece: u8) -> u8 {
-    piece & 0b11000
+use std::num::NonZeroU8;
+use std::ops::Not;
+
+const WHITE_BIT: u8 = 0b01000;
+const BLACK_BIT: u8 = 0b10000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    // index into per-color arrays: 0 for white, 1 for black
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+
+    pub const fn opposite(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+impl Not for Color {
+    type Output = Color;
+
+    fn not(self) -> Color {
+        self.opposite()
+    }
 }
 
-pub fn get_piece_type(piece: u8) -> u8 {
-    piece & 0b00111
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PieceKind {
+    Pawn = 1,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
 }
 
-#[allow(dead_code)]
-const PIECES_STRING: &str = ".pnbrqkPNBRQK";
+impl PieceKind {
+    pub const ALL: [PieceKind; 6] = [
+        PieceKind::Pawn,
+        PieceKind::Knight,
+        PieceKind::Bishop,
+        PieceKind::Rook,
+        PieceKind::Queen,
+        PieceKind::King,
+    ];
+
+    // 1..=6, index 0 is left for an empty square in piece tables
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_char(char: char) -> Option<PieceKind> {
+        match char.to_ascii_lowercase() {
+            'p' => Some(PieceKind::Pawn),
+            'n' => Some(PieceKind::Knight),
+            'b' => Some(PieceKind::Bishop),
+            'r' => Some(PieceKind::Rook),
+            'q' => Some(PieceKind::Queen),
+            'k' => Some(PieceKind::King),
+            _ => None,
+        }
+    }
 
-#[allow(dead_code)]
-pub fn get_piece_char(piece: u8) -> char {
-    let piece_type = get_piece_type(piece);
-    let is_white = get_piece_color(piece) == WHITE;
-    let n = piece_type + (is_white as u8) * 6;
-    PIECES_STRING.chars().nth(n as usize).unwrap()
+    // lowercase letter, as used for promotions in UCI moves
+    pub fn to_char(self) -> char {
+        b".pnbrqk"[self.index()] as char
+    }
 }
 
-pub fn piece_from_char(char: char) -> u8 {
-    let is_white = char.is_ascii_uppercase();
-    let piece = match char.to_ascii_lowercase() {
-        'p' => PAWN,
-        'n' => KNIGHT,
-        'b' => BISHOP,
-        'r' => ROOK,
-        'q' => QUEEN,
-        'k' => KING,
-        _ => EMPTY,
-    };
-    if is_white {
-        piece | WHITE
-    } else {
-        piece | BLACK
+// Same bit coding as in design_doc.md: piece kind in the low three bits,
+// then one bit for white and one for black, so a piece is never zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Piece(NonZeroU8);
+
+impl Piece {
+    pub const fn new(color: Color, kind: PieceKind) -> Piece {
+        let color_bit = match color {
+            Color::White => WHITE_BIT,
+            Color::Black => BLACK_BIT,
+        };
+        match NonZeroU8::new(color_bit | kind as u8) {
+            Some(bits) => Piece(bits),
+            None => unreachable!(),
+        }
+    }
+
+    pub const fn color(self) -> Color {
+        if self.0.get() & BLACK_BIT != 0 {
+            Color::Black
+        } else {
+            Color::White
+        }
+    }
+
+    pub const fn kind(self) -> PieceKind {
+        match self.0.get() & 0b00111 {
+            1 => PieceKind::Pawn,
+            2 => PieceKind::Knight,
+            3 => PieceKind::Bishop,
+            4 => PieceKind::Rook,
+            5 => PieceKind::Queen,
+            _ => PieceKind::King,
+        }
+    }
+
+    // piece code 9..=22, index into per-piece tables of size 24
+    pub const fn index(self) -> usize {
+        self.0.get() as usize
+    }
+
+    pub fn from_char(char: char) -> Option<Piece> {
+        let color = if char.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        PieceKind::from_char(char).map(|kind| Piece::new(color, kind))
+    }
+
+    // FEN letter, uppercase for white
+    pub fn to_char(self) -> char {
+        let char = self.kind().to_char();
+        match self.color() {
+            Color::White => char.to_ascii_uppercase(),
+            Color::Black => char,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_piece_conversions() {
+        let black_knight = Piece::from_char('n').unwrap();
+        assert_eq!(black_knight, Piece::new(Color::Black, PieceKind::Knight));
+        assert_eq!(black_knight.index(), 0b10010);
+        assert_eq!(black_knight.to_char(), 'n');
+        assert_eq!(Piece::from_char('Q').unwrap().color(), Color::White);
+        assert_eq!(Piece::from_char('Q').unwrap().kind(), PieceKind::Queen);
+        assert_eq!(Piece::from_char('x'), None);
+        assert_eq!(!Color::White, Color::Black);
+        assert_eq!(std::mem::size_of::<Option<Piece>>(), 1);
     }
 }
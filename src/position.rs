@@ -1,418 +1,789 @@
-use crate::hash::ZobristKeys;
-use crate::movegen::{BOARD_SQUARES, Move, get_file, get_rank, get_square_string, is_off_board};
-use crate::piece::*;
+use crate::bitboard::{
+    KING_ATTACKS, KNIGHT_ATTACKS, LIGHT_SQUARES, PAWN_ATTACKS, bishop_attacks, pop_lsb,
+    rook_attacks, square_bit,
+};
+use crate::hash::{ZOBRIST_KEYS, ZobristKeys};
+use crate::movegen::{
+    GenType, Move, attackers, get_castling_targets, get_chess960_move_string, get_move_string,
+    sole_blockers,
+};
+use crate::movelist::MoveList;
+use crate::piece::{Color, Piece, PieceKind};
+use crate::san::MoveParseError;
+use crate::square::{BOARD_SQUARES, Square};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    MissingFields(usize),
+    WrongRankCount(usize),
+    TooManySquares(usize),
+    TooFewSquares(usize),
+    InvalidPiece(char),
+    MissingKing(Color),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+}
+
+impl std::fmt::Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FenError::MissingFields(n) => write!(f, "expected at least 4 fields, found {}", n),
+            FenError::WrongRankCount(n) => write!(f, "expected 8 ranks, found {}", n),
+            FenError::TooManySquares(rank) => write!(f, "too many squares on rank {}", rank),
+            FenError::TooFewSquares(rank) => write!(f, "too few squares on rank {}", rank),
+            FenError::InvalidPiece(c) => write!(f, "invalid piece character '{}'", c),
+            FenError::MissingKing(color) => {
+                write!(f, "expected exactly one {:?} king", color)
+            }
+            FenError::InvalidSideToMove(s) => write!(f, "invalid side to move '{}'", s),
+            FenError::InvalidCastling(s) => write!(f, "invalid castling rights '{}'", s),
+            FenError::InvalidEnPassant(s) => write!(f, "invalid en passant square '{}'", s),
+            FenError::InvalidHalfmoveClock(s) => write!(f, "invalid halfmove clock '{}'", s),
+            FenError::InvalidFullmoveNumber(s) => write!(f, "invalid fullmove number '{}'", s),
+        }
+    }
+}
 
-pub fn get_square_in_64(square_in_128: usize) -> usize {
-    get_rank(square_in_128) * 8 + get_file(square_in_128)
+impl std::error::Error for FenError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    FiftyMoves,
+    ThreefoldRepetition,
+    InsufficientMaterial,
+}
+
+// How a finished game ended, winner is None for draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    pub winner: Option<Color>,
+    pub termination: Termination,
 }
 
+impl Outcome {
+    fn draw(termination: Termination) -> Self {
+        Self {
+            winner: None,
+            termination,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
 pub struct Position {
-    pub board: [u8; 128],
-    pub is_white_turn: bool,
-    pub enpassant_square: Option<usize>,
+    pub(crate) board: [Option<Piece>; 128],
+    // indexed by Piece::index, kept in sync with board
+    pub bitboards: [u64; 24],
+    pub occupancy: [u64; 2], // white, black
+    pub side_to_move: Color,
+    pub enpassant_square: Option<Square>,
     // white kingside, white queenside, black kingside, black queenside
     pub castling_rights: [bool; 4],
-    pub king_squares: [usize; 2], // white, black
-    pub keys: ZobristKeys,
+    // rook start squares for the castling rights above, arbitrary in Chess960
+    pub castling_rooks: [Square; 4],
+    // write castling in UCI moves as king takes rook (UCI_Chess960)
+    pub chess960: bool,
+    pub king_squares: [Square; 2], // white, black
     pub hash: u64,
-    pub repetition_stack: [u64; 512],
-    pub repetition_index: usize,
     pub fifty: u8,
+    pub fullmove_number: u32,
+}
 
-    prev_target_piece: [u8; 64],
-    prev_castling_rights: [[bool; 4]; 64],
-    prev_king_squares: [[usize; 2]; 64],
-    prev_ep_square: [Option<usize>; 64],
-    prev_hash: [u64; 64],
-    prev_fifty: [u8; 64],
+// State that make_move can't recompute when taking a move back,
+// returned by make_move and handed back to unmake_move.
+#[derive(Debug, Clone, Copy)]
+pub struct UndoInfo {
+    captured_piece: Option<Piece>,
+    castling_rights: [bool; 4],
+    king_squares: [Square; 2],
+    enpassant_square: Option<Square>,
+    hash: u64,
+    fifty: u8,
 }
 
 impl Position {
     #[allow(dead_code)]
     pub fn print(&self) {
-        let side_to_move = match self.is_white_turn {
-            true => "White",
-            false => "Black",
-        };
-        print!("{} to move", side_to_move);
+        print!("{:?} to move", self.side_to_move);
         print!(" castling {:?}", self.castling_rights);
         print!(" king squares {:?}", self.king_squares);
         let ep_square = match self.enpassant_square {
-            Some(square) => get_square_string(square),
+            Some(square) => square.to_string(),
             None => "_".to_string(),
         };
         print!(" EP square {}", ep_square);
         print!(" hm {}", self.fifty);
 
-        let mut rank = 8;
-        for i in BOARD_SQUARES {
-            if i % 16 == 0 {
-                print!("\n{} ", rank);
-                rank -= 1;
+        for square in BOARD_SQUARES {
+            if square.file() == 0 {
+                print!("\n{} ", square.rank() + 1);
             }
-            print!("{} ", get_piece_char(self.board[i]));
+            let piece_char = self.piece_at(square).map_or('.', Piece::to_char);
+            print!("{} ", piece_char);
         }
         println!("\n  a b c d e f g h");
     }
 
-    pub fn is_repetition(&self) -> bool {
-        for i in 0..self.repetition_index {
-            if self.repetition_stack[i] == self.hash {
-                return true;
-            }
+    // the game result if the game is over. Position doesn't keep the game, so
+    // threefold repetition is only found from hash_history, the hashes of the
+    // positions played before this one as the caller keeps them
+    pub fn outcome(&self, hash_history: &[u64]) -> Option<Outcome> {
+        if self.generate_legal_moves().is_empty() {
+            return Some(if self.in_check() {
+                Outcome {
+                    winner: Some(!self.side_to_move),
+                    termination: Termination::Checkmate,
+                }
+            } else {
+                Outcome::draw(Termination::Stalemate)
+            });
+        }
+        if self.is_insufficient_material() {
+            Some(Outcome::draw(Termination::InsufficientMaterial))
+        } else if self.fifty >= 100 {
+            Some(Outcome::draw(Termination::FiftyMoves))
+        } else if self.repetition_count(hash_history) >= 3 {
+            Some(Outcome::draw(Termination::ThreefoldRepetition))
+        } else {
+            None
         }
-        false
+    }
+
+    // times the current position has occurred, itself included
+    pub fn repetition_count(&self, hash_history: &[u64]) -> usize {
+        let earlier = hash_history
+            .iter()
+            .filter(|&&hash| hash == self.hash)
+            .count();
+        earlier + 1
+    }
+
+    // neither side can ever mate: bare kings, a single minor piece, or only
+    // bishops that all stand on the same color
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy_or_pawns = [PieceKind::Pawn, PieceKind::Rook, PieceKind::Queen]
+            .iter()
+            .any(|&kind| self.pieces(Color::White, kind) | self.pieces(Color::Black, kind) != 0);
+        if heavy_or_pawns {
+            return false;
+        }
+        let knights = self.pieces(Color::White, PieceKind::Knight)
+            | self.pieces(Color::Black, PieceKind::Knight);
+        let bishops = self.pieces(Color::White, PieceKind::Bishop)
+            | self.pieces(Color::Black, PieceKind::Bishop);
+        let minors = knights | bishops;
+        minors.count_ones() <= 1
+            || (knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0))
+    }
+
+    // dead positions, and two knights against a bare king which can't force mate
+    pub fn is_material_draw(&self) -> bool {
+        if self.is_insufficient_material() {
+            return true;
+        }
+        let only_knights = |color: Color| {
+            self.occupancy[color.index()]
+                == self.pieces(color, PieceKind::Knight) | self.pieces(color, PieceKind::King)
+        };
+        let bare_king = |color: Color| self.occupancy[color.index()].count_ones() == 1;
+        [Color::White, Color::Black].iter().any(|&color| {
+            only_knights(color)
+                && self.pieces(color, PieceKind::Knight).count_ones() == 2
+                && bare_king(!color)
+        })
+    }
+
+    // hash_history holds the hashes of the positions played before this one,
+    // only those since the last capture or pawn move can repeat
+    pub fn is_repetition(&self, hash_history: &[u64]) -> bool {
+        hash_history
+            .iter()
+            .rev()
+            .take(self.fifty as usize)
+            .any(|&hash| hash == self.hash)
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.board[square.index()]
+    }
+
+    pub fn pieces(&self, color: Color, kind: PieceKind) -> u64 {
+        self.bitboards[Piece::new(color, kind).index()]
+    }
+
+    pub fn occupied(&self) -> u64 {
+        self.occupancy[0] | self.occupancy[1]
+    }
+
+    // Attack queries, all bitboards use the 64-square index.
+
+    // squares of the pieces of color attacking square
+    pub fn attackers_to(&self, square: Square, color: Color) -> u64 {
+        attackers(self, square.to_64(), self.occupied(), color)
+    }
+
+    // squares of the enemy pieces giving check to the side to move
+    pub fn checkers(&self) -> u64 {
+        let us = self.side_to_move;
+        self.attackers_to(self.king_squares[us.index()], !us)
+    }
+
+    pub fn in_check(&self) -> bool {
+        self.checkers() != 0
+    }
+
+    // pieces of color that are pinned to their own king
+    pub fn pinned(&self, color: Color) -> u64 {
+        let king = self.king_squares[color.index()].to_64();
+        sole_blockers(self, king, !color) & self.occupancy[color.index()]
+    }
+
+    // every square attacked by a piece of color, defended own pieces included
+    pub fn attack_map(&self, color: Color) -> u64 {
+        let occupancy = self.occupied();
+        let mut attacks = KING_ATTACKS[self.king_squares[color.index()].to_64()];
+        let mut pawns = self.pieces(color, PieceKind::Pawn);
+        while pawns != 0 {
+            attacks |= PAWN_ATTACKS[color.index()][pop_lsb(&mut pawns)];
+        }
+        let mut knights = self.pieces(color, PieceKind::Knight);
+        while knights != 0 {
+            attacks |= KNIGHT_ATTACKS[pop_lsb(&mut knights)];
+        }
+        let queens = self.pieces(color, PieceKind::Queen);
+        let mut bishops = self.pieces(color, PieceKind::Bishop) | queens;
+        while bishops != 0 {
+            attacks |= bishop_attacks(pop_lsb(&mut bishops), occupancy);
+        }
+        let mut rooks = self.pieces(color, PieceKind::Rook) | queens;
+        while rooks != 0 {
+            attacks |= rook_attacks(pop_lsb(&mut rooks), occupancy);
+        }
+        attacks
+    }
+
+    // all board writes go through here so the bitboards stay in sync
+    fn set_square(&mut self, square: Square, piece: Option<Piece>) {
+        let bit = square_bit(square.to_64());
+        if let Some(old_piece) = self.board[square.index()] {
+            self.bitboards[old_piece.index()] ^= bit;
+            self.occupancy[old_piece.color().index()] ^= bit;
+        }
+        if let Some(piece) = piece {
+            self.bitboards[piece.index()] |= bit;
+            self.occupancy[piece.color().index()] |= bit;
+        }
+        self.board[square.index()] = piece;
     }
 
     pub fn from_fen(fen_string: &str) -> Self {
+        Self::try_from_fen(fen_string)
+            .unwrap_or_else(|error| panic!("Invalid FEN '{}': {}", fen_string, error))
+    }
+
+    pub fn try_from_fen(fen_string: &str) -> Result<Self, FenError> {
         let mut pos = Position {
-            board: [EMPTY; 128],
-            is_white_turn: false,
+            board: [None; 128],
+            bitboards: [0u64; 24],
+            occupancy: [0u64; 2],
+            side_to_move: Color::White,
             enpassant_square: None,
             castling_rights: [false, false, false, false],
-            king_squares: [127, 127], // we dont know yet
-            keys: ZobristKeys::new(),
-            hash: 0u64, // not generated yet
-            repetition_stack: [0u64; 512],
-            repetition_index: 0,
+            castling_rooks: [
+                Square::from_index(119),
+                Square::from_index(112),
+                Square::from_index(7),
+                Square::from_index(0),
+            ],
+            chess960: false,
+            king_squares: [Square::default(); 2], // we dont know yet
+            hash: 0u64,                           // not generated yet
             fifty: 0,
-
-            prev_target_piece: [0u8; 64],
-            prev_castling_rights: [[false, false, false, false]; 64],
-            prev_king_squares: [[127, 127]; 64],
-            prev_ep_square: [None; 64],
-            prev_hash: [0u64; 64],
-            prev_fifty: [0u8; 64],
+            fullmove_number: 1,
         };
-        let fen_parts = fen_string.split(" ").collect::<Vec<&str>>();
-        // currently using only the piece placement, later use side, castling, ep, etc.
+        let fen_parts = fen_string.split_ascii_whitespace().collect::<Vec<&str>>();
+        if fen_parts.len() < 4 {
+            return Err(FenError::MissingFields(fen_parts.len()));
+        }
         let piece_placement = fen_parts[0];
         let side_to_move = fen_parts[1];
         let castling_rights = fen_parts[2];
-        let ep_square = fen_parts[3].trim_ascii_end();
+        let ep_square = fen_parts[3];
 
-        if ep_square != "-" {
-            let ep_file = ep_square.chars().next().unwrap() as usize;
-            let ep_rank = ep_square.chars().nth(1).unwrap().to_digit(10).unwrap() as usize;
-            let ep_square_128 = (ep_file - 97) + ((8 - ep_rank) * 16);
-            pos.enpassant_square = Some(ep_square_128);
+        let ranks = piece_placement.split('/').collect::<Vec<&str>>();
+        if ranks.len() != 8 {
+            return Err(FenError::WrongRankCount(ranks.len()));
+        }
+        for (row, rank_string) in ranks.iter().enumerate() {
+            let rank_number = 8 - row;
+            let mut file = 0;
+            for c in rank_string.chars() {
+                if file >= 8 {
+                    return Err(FenError::TooManySquares(rank_number));
+                }
+                if let Some(n_empty_squares) = c.to_digit(10).filter(|n| (1..=8).contains(n)) {
+                    file += n_empty_squares as usize;
+                    continue;
+                }
+                let Some(piece) = Piece::from_char(c) else {
+                    return Err(FenError::InvalidPiece(c));
+                };
+                let square = Square::from_file_rank(file, rank_number - 1);
+                if piece.kind() == PieceKind::King {
+                    pos.king_squares[piece.color().index()] = square;
+                }
+                pos.set_square(square, Some(piece));
+                file += 1;
+            }
+            match file {
+                8 => {}
+                0..8 => return Err(FenError::TooFewSquares(rank_number)),
+                _ => return Err(FenError::TooManySquares(rank_number)),
+            }
+        }
+        for color in [Color::White, Color::Black] {
+            if pos.pieces(color, PieceKind::King).count_ones() != 1 {
+                return Err(FenError::MissingKing(color));
+            }
         }
 
-        pos.is_white_turn = side_to_move == "w";
+        pos.side_to_move = match side_to_move {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(FenError::InvalidSideToMove(side_to_move.to_string())),
+        };
 
-        let mut i: usize = 0;
-        for c in piece_placement.chars() {
-            if c.is_numeric() {
-                let n_empty_squares = c.to_digit(10).unwrap() as usize;
-                i += n_empty_squares;
-            } else if c == '/' {
-                i += 8;
-            } else {
-                if c == 'K' {
-                    pos.king_squares[0] = i;
-                } else if c == 'k' {
-                    pos.king_squares[1] = i;
+        // KQkq, X-FEN and Shredder-FEN castling fields
+        if castling_rights != "-" {
+            let invalid_castling = || FenError::InvalidCastling(castling_rights.to_string());
+            for c in castling_rights.chars() {
+                let color = if c.is_ascii_uppercase() {
+                    Color::White
+                } else {
+                    Color::Black
+                };
+                let back_rank = if color == Color::White { 0 } else { 7 };
+                let king_square = pos.king_squares[color.index()];
+                if king_square.rank() != back_rank {
+                    return Err(invalid_castling());
                 }
-                let piece = piece_from_char(c);
-                pos.board[i] = piece;
-                i += 1;
+                let rook = Some(Piece::new(color, PieceKind::Rook));
+                let is_rook =
+                    |file: &usize| pos.piece_at(Square::from_file_rank(*file, back_rank)) == rook;
+                let rook_file = match c.to_ascii_lowercase() {
+                    // outermost rook on that side of the king
+                    'k' => (king_square.file() + 1..8).rev().find(is_rook),
+                    'q' => (0..king_square.file()).find(is_rook),
+                    file @ 'a'..='h' => Some(file as usize - 'a' as usize).filter(is_rook),
+                    _ => None,
+                };
+                let Some(rook_file) = rook_file else {
+                    return Err(invalid_castling());
+                };
+                let is_kingside = rook_file > king_square.file();
+                let idx = color.index() * 2 + if is_kingside { 0 } else { 1 };
+                if pos.castling_rights[idx] {
+                    return Err(invalid_castling());
+                }
+                pos.castling_rights[idx] = true;
+                pos.castling_rooks[idx] = Square::from_file_rank(rook_file, back_rank);
             }
         }
 
-        for c in castling_rights.chars() {
-            match c {
-                '-' => break,
-                'K' => pos.castling_rights[0] = true,
-                'Q' => pos.castling_rights[1] = true,
-                'k' => pos.castling_rights[2] = true,
-                'q' => pos.castling_rights[3] = true,
-                _ => panic!("Unexpected castling rights char: {}", c),
+        if ep_square != "-" {
+            // the en passant square is behind a pawn that just moved two squares
+            let expected_rank = match pos.side_to_move {
+                Color::White => 5,
+                Color::Black => 2,
+            };
+            match Square::from_algebraic(ep_square) {
+                Some(square) if square.rank() == expected_rank => {
+                    pos.enpassant_square = Some(square);
+                }
+                _ => return Err(FenError::InvalidEnPassant(ep_square.to_string())),
             }
         }
-        pos.generate_hash();
-        pos
-    }
 
-    fn piece_hash(&self, square: usize, piece: u8) -> u64 {
-        // empty squares don't change hash
-        if get_piece_type(piece) == EMPTY {
-            return 0;
+        // halfmove clock and fullmove number are optional
+        if let Some(halfmove_clock) = fen_parts.get(4) {
+            pos.fifty = halfmove_clock
+                .parse()
+                .map_err(|_| FenError::InvalidHalfmoveClock(halfmove_clock.to_string()))?;
         }
-        let square64 = get_square_in_64(square);
-        self.keys.piece_keys[square64][piece as usize]
+        if let Some(fullmove_number) = fen_parts.get(5) {
+            pos.fullmove_number = fullmove_number
+                .parse()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| FenError::InvalidFullmoveNumber(fullmove_number.to_string()))?;
+        }
+
+        pos.generate_hash();
+        Ok(pos)
     }
 
-    fn generate_hash(&mut self) {
-        // Hash pieces
-        for square in BOARD_SQUARES {
-            let piece = self.board[square];
-            if get_piece_type(piece) != EMPTY {
-                self.hash ^= self.piece_hash(square, piece);
+    pub fn to_fen(&self) -> String {
+        let mut piece_placement = String::new();
+        let mut empty_squares = 0;
+        for (i, square) in BOARD_SQUARES.into_iter().enumerate() {
+            if let Some(piece) = self.piece_at(square) {
+                if empty_squares > 0 {
+                    piece_placement.push_str(&empty_squares.to_string());
+                    empty_squares = 0;
+                }
+                piece_placement.push(piece.to_char());
+            } else {
+                empty_squares += 1;
+            }
+            if square.file() == 7 {
+                if empty_squares > 0 {
+                    piece_placement.push_str(&empty_squares.to_string());
+                    empty_squares = 0;
+                }
+                if i != 63 {
+                    piece_placement.push('/');
+                }
             }
         }
 
-        // Hash side to move
-        if !self.is_white_turn {
-            self.hash ^= self.keys.black_to_move_key;
+        let side_to_move = match self.side_to_move {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+
+        let mut castling_rights = String::new();
+        for (idx, c) in ['K', 'Q', 'k', 'q'].into_iter().enumerate() {
+            if !self.castling_rights[idx] {
+                continue;
+            }
+            // X-FEN: use the rook's file only when another rook is further out on that side
+            let rook_square = self.castling_rooks[idx];
+            let rook = self.piece_at(rook_square);
+            let mut outer_files = if idx % 2 == 0 {
+                rook_square.file() + 1..8
+            } else {
+                0..rook_square.file()
+            };
+            if outer_files
+                .any(|file| self.piece_at(Square::from_file_rank(file, rook_square.rank())) == rook)
+            {
+                let file_char = (b'a' + rook_square.file() as u8) as char;
+                if idx < 2 {
+                    castling_rights.push(file_char.to_ascii_uppercase());
+                } else {
+                    castling_rights.push(file_char);
+                }
+            } else {
+                castling_rights.push(c);
+            }
         }
+        if castling_rights.is_empty() {
+            castling_rights.push('-');
+        }
+
+        let ep_square = match self.enpassant_square {
+            Some(square) => square.to_string(),
+            None => "-".to_string(),
+        };
+
+        format!(
+            "{} {} {} {} {} {}",
+            piece_placement,
+            side_to_move,
+            castling_rights,
+            ep_square,
+            self.fifty,
+            self.fullmove_number
+        )
+    }
 
-        // Hash en passant file (if any)
-        if let Some(ep_square) = self.enpassant_square {
-            let ep_file = get_file(ep_square);
-            self.hash ^= self.keys.enpassant_file_keys[ep_file];
+    fn piece_hash(&self, square: Square, piece: Piece) -> u64 {
+        ZOBRIST_KEYS.piece_keys[square.to_64()][piece.index()]
+    }
+
+    // Like Polyglot, the en passant file only counts when a pawn of the side
+    // to move stands ready to take, pins aside.
+    fn enpassant_key(&self, keys: &ZobristKeys) -> u64 {
+        match self.enpassant_square {
+            Some(ep_square)
+                if PAWN_ATTACKS[(!self.side_to_move).index()][ep_square.to_64()]
+                    & self.pieces(self.side_to_move, PieceKind::Pawn)
+                    != 0 =>
+            {
+                keys.enpassant_file_keys[ep_square.file()]
+            }
+            _ => 0,
         }
+    }
 
-        // Hash castling rights
+    // the hash of this position under another key set, built with
+    // ZobristKeys::from_random64
+    pub fn hash_with(&self, keys: &ZobristKeys) -> u64 {
+        let mut hash = 0;
+        for square in BOARD_SQUARES {
+            if let Some(piece) = self.piece_at(square) {
+                hash ^= keys.piece_keys[square.to_64()][piece.index()];
+            }
+        }
+        if self.side_to_move == Color::White {
+            hash ^= keys.white_to_move_key;
+        }
+        hash ^= self.enpassant_key(keys);
         for (idx, &has_right) in self.castling_rights.iter().enumerate() {
             if has_right {
-                self.hash ^= self.keys.castling_rights_keys[idx];
+                hash ^= keys.castling_rights_keys[idx];
             }
         }
+        hash
     }
 
-    #[allow(dead_code)]
-    pub fn generate_pseudo_moves(&self) -> Vec<Move> {
-        crate::movegen::generate_pseudo_moves(self, false)
+    fn generate_hash(&mut self) {
+        self.hash = self.hash_with(&ZOBRIST_KEYS);
     }
 
-    pub fn generate_tactical_moves(&self) -> Vec<Move> {
-        crate::movegen::generate_pseudo_moves(self, true)
+    pub fn uci_move_string(&self, move_: &Move) -> String {
+        if self.chess960 {
+            get_chess960_move_string(move_)
+        } else {
+            get_move_string(move_)
+        }
     }
 
-    pub fn generate_legal_moves(&mut self) -> Vec<Move> {
+    #[allow(dead_code)]
+    pub fn generate_legal_moves(&self) -> MoveList {
         crate::movegen::generate_legal_moves(self)
     }
 
-    fn side_has_castling_rights(&self) -> bool {
-        if self.is_white_turn {
-            self.castling_rights[0] || self.castling_rights[1]
+    // legal captures and promotions
+    pub fn generate_tactical_moves(&self) -> MoveList {
+        crate::movegen::generate_moves(self, GenType::Tactical)
+    }
+
+    // legal moves out of check, only valid when the side to move is in check
+    pub fn generate_evasions(&self) -> MoveList {
+        crate::movegen::generate_evasions(self)
+    }
+
+    // legal quiet moves that give check
+    pub fn generate_quiet_checks(&self) -> MoveList {
+        crate::movegen::generate_quiet_checks(self)
+    }
+
+    // whether a legal move checks the opponent, directly or by discovery
+    pub fn gives_check(&self, move_: &Move) -> bool {
+        crate::movegen::gives_check(self, move_)
+    }
+
+    // whether the check comes from a piece the move uncovers, castling rook aside
+    pub fn is_discovered_check(&self, move_: &Move) -> bool {
+        crate::movegen::is_discovered_check(self, move_)
+    }
+
+    pub fn move_to_san(&self, move_: &Move) -> String {
+        crate::san::move_to_san(self, move_)
+    }
+
+    pub fn parse_san(&self, san: &str) -> Result<Move, MoveParseError> {
+        crate::san::parse_san(self, san)
+    }
+
+    pub fn moved_piece(&self, move_: &Move) -> Option<Piece> {
+        self.piece_at(move_.from())
+    }
+
+    // the piece a move takes, castling takes nothing even though it lands on the rook
+    pub fn captured_piece(&self, move_: &Move) -> Option<Piece> {
+        if move_.is_enpassant() {
+            Some(Piece::new(!self.side_to_move, PieceKind::Pawn))
+        } else if move_.is_capture() {
+            self.piece_at(move_.to())
         } else {
-            self.castling_rights[2] || self.castling_rights[3]
+            None
         }
     }
 
+    fn side_has_castling_rights(&self) -> bool {
+        let idx = self.side_to_move.index() * 2;
+        self.castling_rights[idx] || self.castling_rights[idx + 1]
+    }
+
+    // castling moves are encoded as the king taking its own rook
     fn handle_castling_move(&mut self, move_: &Move) {
         // castling rights removal is handled when king moves
-        match move_.to {
-            118 => {
-                self.board[119] = EMPTY;
-                self.board[117] = WHITE | ROOK;
-                self.hash ^= self.piece_hash(119, WHITE | ROOK);
-                self.hash ^= self.piece_hash(117, WHITE | ROOK);
-            }
-            114 => {
-                self.board[112] = EMPTY;
-                self.board[115] = WHITE | ROOK;
-                self.hash ^= self.piece_hash(112, WHITE | ROOK);
-                self.hash ^= self.piece_hash(115, WHITE | ROOK);
-            }
-            6 => {
-                self.board[7] = EMPTY;
-                self.board[5] = BLACK | ROOK;
-                self.hash ^= self.piece_hash(7, BLACK | ROOK);
-                self.hash ^= self.piece_hash(5, BLACK | ROOK);
-            }
-            2 => {
-                self.board[0] = EMPTY;
-                self.board[3] = BLACK | ROOK;
-                self.hash ^= self.piece_hash(0, BLACK | ROOK);
-                self.hash ^= self.piece_hash(3, BLACK | ROOK);
-            }
-            _ => panic!("invalid square to move to"),
-        }
+        let (king_to, rook_to) = get_castling_targets(move_);
+        let king = Piece::new(self.side_to_move, PieceKind::King);
+        let rook = Piece::new(self.side_to_move, PieceKind::Rook);
+        self.set_square(move_.from(), None);
+        self.set_square(move_.to(), None);
+        self.set_square(king_to, Some(king));
+        self.set_square(rook_to, Some(rook));
+        self.hash ^= self.piece_hash(move_.from(), king);
+        self.hash ^= self.piece_hash(move_.to(), rook);
+        self.hash ^= self.piece_hash(king_to, king);
+        self.hash ^= self.piece_hash(rook_to, rook);
+        self.king_squares[self.side_to_move.index()] = king_to;
     }
 
     fn revert_castling_move(&mut self, move_: &Move) {
-        match move_.to {
-            118 => {
-                self.board[119] = WHITE | ROOK;
-                self.board[117] = EMPTY;
-            }
-            114 => {
-                self.board[112] = WHITE | ROOK;
-                self.board[115] = EMPTY;
-            }
-            6 => {
-                self.board[7] = BLACK | ROOK;
-                self.board[5] = EMPTY;
-            }
-            2 => {
-                self.board[0] = BLACK | ROOK;
-                self.board[3] = EMPTY;
-            }
-            _ => panic!("invalid square to move to"),
-        }
+        let (king_to, rook_to) = get_castling_targets(move_);
+        let king = self.piece_at(king_to);
+        let rook = self.piece_at(rook_to);
+        self.set_square(king_to, None);
+        self.set_square(rook_to, None);
+        self.set_square(move_.from(), king);
+        self.set_square(move_.to(), rook);
     }
 
-    pub fn make_move(&mut self, move_: &Move, ply: u32) {
-        self.prev_target_piece[ply as usize] = self.board[move_.to];
-        self.prev_castling_rights[ply as usize] = self.castling_rights;
-        self.prev_king_squares[ply as usize] = self.king_squares;
-        self.prev_ep_square[ply as usize] = self.enpassant_square;
-        self.prev_hash[ply as usize] = self.hash;
-        self.prev_fifty[ply as usize] = self.fifty;
+    pub fn make_move(&mut self, move_: &Move) -> UndoInfo {
+        let undo = UndoInfo {
+            captured_piece: self.piece_at(move_.to()),
+            castling_rights: self.castling_rights,
+            king_squares: self.king_squares,
+            enpassant_square: self.enpassant_square,
+            hash: self.hash,
+            fifty: self.fifty,
+        };
 
-        let piece = self.board[move_.from];
-        let piece_type = get_piece_type(piece);
+        let piece = self
+            .piece_at(move_.from())
+            .expect("no piece on from square");
+        let piece_type = piece.kind();
         // remove previous en passant square from hash
-        if let Some(ep_square) = self.enpassant_square {
-            self.hash ^= self.keys.enpassant_file_keys[get_file(ep_square)];
-        }
+        self.hash ^= self.enpassant_key(&ZOBRIST_KEYS);
         self.enpassant_square = None;
         self.fifty += 1;
 
-        if piece_type == PAWN {
+        if piece_type == PieceKind::Pawn {
             self.fifty = 0;
         }
 
         if self.side_has_castling_rights() {
             // lose castling rights when king moves
-            if piece_type == KING {
-                match self.is_white_turn {
-                    true => {
-                        if self.castling_rights[0] {
-                            self.hash ^= self.keys.castling_rights_keys[0];
-                            self.castling_rights[0] = false;
-                        }
-                        if self.castling_rights[1] {
-                            self.hash ^= self.keys.castling_rights_keys[1];
-                            self.castling_rights[1] = false;
-                        }
-                    }
-                    false => {
-                        if self.castling_rights[2] {
-                            self.hash ^= self.keys.castling_rights_keys[2];
-                            self.castling_rights[2] = false;
-                        }
-                        if self.castling_rights[3] {
-                            self.hash ^= self.keys.castling_rights_keys[3];
-                            self.castling_rights[3] = false;
-                        }
+            if piece_type == PieceKind::King {
+                let first_idx = self.side_to_move.index() * 2;
+                for idx in [first_idx, first_idx + 1] {
+                    if self.castling_rights[idx] {
+                        self.hash ^= ZOBRIST_KEYS.castling_rights_keys[idx];
+                        self.castling_rights[idx] = false;
                     }
                 }
             }
         }
         // lose castling rights when rook moves or gets captured
-        if self.castling_rights[0] && (move_.from == 119 || move_.to == 119) {
-            self.castling_rights[0] = false;
-            self.hash ^= self.keys.castling_rights_keys[0];
-        }
-        if self.castling_rights[1] && (move_.from == 112 || move_.to == 112) {
-            self.castling_rights[1] = false;
-            self.hash ^= self.keys.castling_rights_keys[1];
-        }
-        if self.castling_rights[2] && (move_.from == 7 || move_.to == 7) {
-            self.castling_rights[2] = false;
-            self.hash ^= self.keys.castling_rights_keys[2];
-        }
-        if self.castling_rights[3] && (move_.from == 0 || move_.to == 0) {
-            self.castling_rights[3] = false;
-            self.hash ^= self.keys.castling_rights_keys[3];
+        for idx in 0..4 {
+            let rook_square = self.castling_rooks[idx];
+            if self.castling_rights[idx]
+                && (move_.from() == rook_square || move_.to() == rook_square)
+            {
+                self.castling_rights[idx] = false;
+                self.hash ^= ZOBRIST_KEYS.castling_rights_keys[idx];
+            }
         }
 
-        if move_.is_castling {
+        if move_.is_castling() {
             self.handle_castling_move(move_);
-        }
-
-        if piece_type == KING {
-            match self.is_white_turn {
-                true => self.king_squares[0] = move_.to,
-                false => self.king_squares[1] = move_.to,
+        } else {
+            if piece_type == PieceKind::King {
+                self.king_squares[self.side_to_move.index()] = move_.to();
             }
-        }
 
-        if move_.is_double_pawn {
-            for dir in [-1, 1] {
-                let square_to_check = move_.to.wrapping_add_signed(dir);
-                if is_off_board(square_to_check) {
-                    continue;
-                }
-                let target_piece = self.board[square_to_check];
-                if self.is_white_turn && target_piece == BLACK | PAWN {
-                    self.enpassant_square = Some(move_.to + 16);
-                    self.hash ^= self.keys.enpassant_file_keys[get_file(move_.to + 16)]
-                } else if !self.is_white_turn && target_piece == WHITE | PAWN {
-                    self.enpassant_square = Some(move_.to - 16);
-                    self.hash ^= self.keys.enpassant_file_keys[get_file(move_.to - 16)]
+            if move_.is_double_pawn() {
+                // only set en passant square when an enemy pawn could capture
+                let enemy_pawn = Some(Piece::new(!self.side_to_move, PieceKind::Pawn));
+                for dir in [-1, 1] {
+                    if let Some(square_to_check) = move_.to().offset(dir)
+                        && self.piece_at(square_to_check) == enemy_pawn
+                    {
+                        let ep_square = Square::from_file_rank(
+                            move_.to().file(),
+                            (move_.from().rank() + move_.to().rank()) / 2,
+                        );
+                        self.enpassant_square = Some(ep_square);
+                        self.hash ^= ZOBRIST_KEYS.enpassant_file_keys[ep_square.file()];
+                        break;
+                    }
                 }
             }
-        }
-        if move_.is_enpassant {
-            if self.is_white_turn {
-                self.board[move_.to + 16] = EMPTY;
-                self.hash ^= self.piece_hash(move_.to + 16, BLACK | PAWN);
-            } else {
-                self.board[move_.to - 16] = EMPTY;
-                self.hash ^= self.piece_hash(move_.to - 16, WHITE | PAWN);
+            if move_.is_enpassant() {
+                let captured_square =
+                    Square::from_file_rank(move_.to().file(), move_.from().rank());
+                let captured_pawn = Piece::new(!self.side_to_move, PieceKind::Pawn);
+                self.set_square(captured_square, None);
+                self.hash ^= self.piece_hash(captured_square, captured_pawn);
             }
-        }
 
-        if move_.is_capture {
-            let target_piece = self.board[move_.to];
-            self.hash ^= self.piece_hash(move_.to, target_piece);
-            self.fifty = 0;
-        }
+            if move_.is_capture()
+                && let Some(target_piece) = self.piece_at(move_.to())
+            {
+                self.hash ^= self.piece_hash(move_.to(), target_piece);
+                self.fifty = 0;
+            }
 
-        if let Some(prom_piece) = move_.promoted_piece {
-            self.board[move_.to] = prom_piece;
-            self.hash ^= self.piece_hash(move_.to, prom_piece);
-        } else {
-            self.board[move_.to] = piece;
-            self.hash ^= self.piece_hash(move_.to, piece);
-        }
+            let moved_piece = match move_.promoted_piece() {
+                Some(kind) => Piece::new(self.side_to_move, kind),
+                None => piece,
+            };
+            self.set_square(move_.to(), Some(moved_piece));
+            self.hash ^= self.piece_hash(move_.to(), moved_piece);
 
-        self.board[move_.from] = EMPTY;
-        self.hash ^= self.piece_hash(move_.from, piece);
-        self.is_white_turn = !self.is_white_turn;
-        self.hash ^= self.keys.black_to_move_key;
+            self.set_square(move_.from(), None);
+            self.hash ^= self.piece_hash(move_.from(), piece);
+        }
+        if self.side_to_move == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.side_to_move = !self.side_to_move;
+        self.hash ^= ZOBRIST_KEYS.white_to_move_key;
+        undo
     }
 
-    pub fn unmake_move(&mut self, move_: &Move, ply: u32) {
-        if move_.is_castling {
-            self.revert_castling_move(move_);
+    pub fn unmake_move(&mut self, move_: &Move, undo: UndoInfo) {
+        self.side_to_move = !self.side_to_move;
+        if self.side_to_move == Color::Black {
+            self.fullmove_number -= 1;
         }
-        let piece = self.board[move_.to];
-        self.board[move_.from] = piece;
-        self.board[move_.to] = self.prev_target_piece[ply as usize];
-        self.is_white_turn = !self.is_white_turn;
-        if move_.is_enpassant {
-            if self.is_white_turn {
-                self.board[move_.to + 16] = BLACK | PAWN;
-            } else {
-                self.board[move_.to - 16] = WHITE | PAWN;
+        if move_.is_castling() {
+            self.revert_castling_move(move_);
+        } else {
+            let piece = self.piece_at(move_.to());
+            self.set_square(move_.from(), piece);
+            self.set_square(move_.to(), undo.captured_piece);
+            if move_.is_enpassant() {
+                let captured_square =
+                    Square::from_file_rank(move_.to().file(), move_.from().rank());
+                let captured_pawn = Piece::new(!self.side_to_move, PieceKind::Pawn);
+                self.set_square(captured_square, Some(captured_pawn));
             }
-        }
-        if move_.promoted_piece.is_some() {
-            if self.is_white_turn {
-                self.board[move_.from] = WHITE | PAWN;
-            } else {
-                self.board[move_.from] = BLACK | PAWN;
+            if move_.is_promotion() {
+                let pawn = Piece::new(self.side_to_move, PieceKind::Pawn);
+                self.set_square(move_.from(), Some(pawn));
             }
         }
-        self.castling_rights = self.prev_castling_rights[ply as usize];
-        self.king_squares = self.prev_king_squares[ply as usize];
-        self.enpassant_square = self.prev_ep_square[ply as usize];
-        self.hash = self.prev_hash[ply as usize];
-        self.fifty = self.prev_fifty[ply as usize];
+        self.castling_rights = undo.castling_rights;
+        self.king_squares = undo.king_squares;
+        self.enpassant_square = undo.enpassant_square;
+        self.hash = undo.hash;
+        self.fifty = undo.fifty;
     }
 
     pub fn make_null(&mut self) {
-        self.is_white_turn = !self.is_white_turn;
-        self.hash ^= self.keys.black_to_move_key;
-
-        // hash enpassant if available (remove enpassant square from hash key )
-        if let Some(ep_square) = self.enpassant_square {
-            self.hash ^= self.keys.enpassant_file_keys[get_file(ep_square)];
-        }
+        // remove enpassant square from hash key while the capturing side is still to move
+        self.hash ^= self.enpassant_key(&ZOBRIST_KEYS);
         self.enpassant_square = None;
+
+        self.side_to_move = !self.side_to_move;
+        self.hash ^= ZOBRIST_KEYS.white_to_move_key;
     }
 
-    pub fn unmake_null(&mut self, copy_ep: Option<usize>) {
-        self.is_white_turn = !self.is_white_turn;
-        self.hash ^= self.keys.black_to_move_key;
-        if let Some(ep_square) = copy_ep {
-            self.hash ^= self.keys.enpassant_file_keys[get_file(ep_square)];
-        }
+    pub fn unmake_null(&mut self, copy_ep: Option<Square>) {
+        self.side_to_move = !self.side_to_move;
+        self.hash ^= ZOBRIST_KEYS.white_to_move_key;
         self.enpassant_square = copy_ep;
+        self.hash ^= self.enpassant_key(&ZOBRIST_KEYS);
     }
 }
@@ -0,0 +1,188 @@
+use crate::{
+    movegen::{Move, get_castling_targets},
+    piece::PieceKind,
+    position::Position,
+    square::Square,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveParseError {
+    Invalid(String),
+    Illegal(String),
+    Ambiguous(String),
+}
+
+impl std::fmt::Display for MoveParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MoveParseError::Invalid(s) => write!(f, "invalid move '{}'", s),
+            MoveParseError::Illegal(s) => write!(f, "illegal move '{}'", s),
+            MoveParseError::Ambiguous(s) => write!(f, "ambiguous move '{}'", s),
+        }
+    }
+}
+
+impl std::error::Error for MoveParseError {}
+
+// Standard Algebraic Notation for a legal move: Nbd7, exd6, e8=Q, O-O-O, Qh4#
+pub fn move_to_san(position: &Position, move_: &Move) -> String {
+    let mut san = String::new();
+    if move_.is_castling() {
+        let (king_to, _) = get_castling_targets(move_);
+        san.push_str(if king_to.file() == 6 { "O-O" } else { "O-O-O" });
+    } else {
+        let kind = position
+            .moved_piece(move_)
+            .expect("no piece on from square")
+            .kind();
+        let from = move_.from().to_algebraic();
+        if kind == PieceKind::Pawn {
+            if move_.is_capture() {
+                san.push_str(&from[..1]);
+            }
+        } else {
+            san.push(kind.to_char().to_ascii_uppercase());
+            san.push_str(&disambiguation(position, move_, kind));
+        }
+        if move_.is_capture() {
+            san.push('x');
+        }
+        san.push_str(&move_.to().to_algebraic());
+        if let Some(promoted_piece) = move_.promoted_piece() {
+            san.push('=');
+            san.push(promoted_piece.to_char().to_ascii_uppercase());
+        }
+    }
+
+    if position.gives_check(move_) {
+        let mut after = *position;
+        after.make_move(move_);
+        san.push(if after.generate_legal_moves().is_empty() {
+            '#'
+        } else {
+            '+'
+        });
+    }
+    san
+}
+
+// file, rank or both of the from square, when another piece of the same kind
+// can also go to the target square
+fn disambiguation(position: &Position, move_: &Move, kind: PieceKind) -> String {
+    let (from, to) = (move_.from(), move_.to());
+    let mut ambiguous = false;
+    let (mut same_file, mut same_rank) = (false, false);
+    for other in &position.generate_legal_moves() {
+        if other.to() != to
+            || other.from() == from
+            || other.is_castling()
+            || position.moved_piece(&other).map(|piece| piece.kind()) != Some(kind)
+        {
+            continue;
+        }
+        ambiguous = true;
+        same_file |= other.from().file() == from.file();
+        same_rank |= other.from().rank() == from.rank();
+    }
+
+    let square = from.to_algebraic();
+    match (ambiguous, same_file, same_rank) {
+        (false, _, _) => String::new(),
+        (true, false, _) => square[..1].to_string(),
+        (true, true, false) => square[1..].to_string(),
+        (true, true, true) => square,
+    }
+}
+
+// a line of moves from position, such as a PV, in SAN
+pub fn line_to_san(position: &Position, moves: &[Move]) -> String {
+    let mut position = *position;
+    let mut sans = Vec::with_capacity(moves.len());
+    for move_ in moves {
+        sans.push(move_to_san(&position, move_));
+        position.make_move(move_);
+    }
+    sans.join(" ")
+}
+
+// A move in SAN, also taking the usual sloppy forms: Nbd7, Ng1-f3, exd6 e.p.,
+// e8=Q, e8Q, e8(Q), 0-0, and any trailing +, #, ! or ?
+pub fn parse_san(position: &Position, san: &str) -> Result<Move, MoveParseError> {
+    let invalid = || MoveParseError::Invalid(san.to_string());
+    let mut text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+    text = text.strip_suffix("e.p.").unwrap_or(text).trim_end();
+    text = text.trim_end_matches(['+', '#']);
+
+    let castling = text.replace('0', "O");
+    let mut candidates = Vec::new();
+    if castling == "O-O" || castling == "O-O-O" {
+        let king_file = if castling == "O-O" { 6 } else { 2 };
+        for move_ in &position.generate_legal_moves() {
+            if move_.is_castling() && get_castling_targets(&move_).0.file() == king_file {
+                candidates.push(move_);
+            }
+        }
+    } else {
+        let mut chars: Vec<char> = text
+            .chars()
+            .filter(|c| !matches!(c, 'x' | ':' | '-'))
+            .collect();
+        let kind = match chars.first() {
+            Some(&c @ ('N' | 'B' | 'R' | 'Q' | 'K' | 'P')) => {
+                chars.remove(0);
+                PieceKind::from_char(c).unwrap()
+            }
+            _ => PieceKind::Pawn,
+        };
+
+        // promotion piece after the target square, with or without = or ()
+        if chars.last() == Some(&')') {
+            chars.pop();
+        }
+        let mut promotion = None;
+        if let Some(&c) = chars.last()
+            && chars.len() > 2
+            && c.is_ascii_alphabetic()
+        {
+            promotion = Some(PieceKind::from_char(c).ok_or_else(invalid)?);
+            chars.pop();
+            if matches!(chars.last(), Some('=' | '(')) {
+                chars.pop();
+            }
+        }
+
+        if chars.len() < 2 || chars.len() > 4 {
+            return Err(invalid());
+        }
+        let target: String = chars[chars.len() - 2..].iter().collect();
+        let to = Square::from_algebraic(&target).ok_or_else(invalid)?;
+        let (mut from_file, mut from_rank) = (None, None);
+        for &c in &chars[..chars.len() - 2] {
+            match c {
+                'a'..='h' if from_file.is_none() && from_rank.is_none() => {
+                    from_file = Some(c as usize - 'a' as usize)
+                }
+                '1'..='8' if from_rank.is_none() => from_rank = Some(c as usize - '1' as usize),
+                _ => return Err(invalid()),
+            }
+        }
+
+        for move_ in &position.generate_legal_moves() {
+            if move_.to() == to
+                && !move_.is_castling()
+                && position.moved_piece(&move_).map(|piece| piece.kind()) == Some(kind)
+                && from_file.is_none_or(|file| move_.from().file() == file)
+                && from_rank.is_none_or(|rank| move_.from().rank() == rank)
+                && move_.promoted_piece() == promotion
+            {
+                candidates.push(move_);
+            }
+        }
+    }
+
+    match candidates[..] {
+        [move_] => Ok(move_),
+        [] => Err(MoveParseError::Illegal(san.to_string())),
+        _ => Err(MoveParseError::Ambiguous(san.to_string())),
+    }
+}
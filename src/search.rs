@@ -3,9 +3,10 @@ use std::time::{Duration, Instant};
 use crate::{
     evaluation::evaluate,
     hash::{NodeType, TranspositionTable},
-    movegen::{Move, get_move_string, is_square_attacked},
-    moveordering::{self},
+    movegen::Move,
+    movepicker::MovePicker,
     position::Position,
+    san::line_to_san,
 };
 
 pub struct Timer {
@@ -24,113 +25,127 @@ impl Timer {
     }
 
     pub fn should_stop(&mut self, node_count: u64) -> bool {
-        if node_count % 2048 == 0 {
+        if node_count.is_multiple_of(2048) {
             self.stopped = self.start_time.elapsed() >= self.max_duration;
         }
         self.stopped
     }
 }
 
-pub fn is_legal(position: &mut Position) -> bool {
-    position.is_white_turn = !position.is_white_turn; // consider from same side before move
-    let idx = if position.is_white_turn { 0 } else { 1 };
-    let is_legal = !is_square_attacked(position.king_squares[idx], position);
-    position.is_white_turn = !position.is_white_turn;
-    is_legal
-}
+// deepest ply the search tables have room for, including quiescence
+pub const MAX_PLY: usize = 128;
+
+// score of the side to move when it is checkmated, and of any drawn position
+pub const MATE_SCORE: i32 = 50000;
+pub const DRAW_SCORE: i32 = 0;
 
 pub struct Search<'a> {
     position: &'a mut Position,
+    // hashes of the game positions before the searched one, then of the current line
+    hash_history: Vec<u64>,
     tt: &'a mut TranspositionTable,
     node_count: u64,
     timer: Timer,
     prev_pv: Vec<Move>,
+    // triangular PV table, row ply holds the best line found from that ply
+    pv_table: [[Move; MAX_PLY]; MAX_PLY],
+    pv_length: [usize; MAX_PLY],
     history: [[u32; 128]; 128],
-    killers: [[Option<Move>; 2]; 64],
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    // also print each PV in SAN as an info string
+    san_pv: bool,
 }
 
 impl<'a> Search<'a> {
     pub fn run(
         position: &'a mut Position,
+        hash_history: &[u64],
         tt: &'a mut TranspositionTable,
         depth: u32,
         movetime: u64,
+        san_pv: bool,
     ) -> (Vec<Move>, u64) {
         tt.clear();
         let max_duration = Duration::from_millis(movetime);
         let mut search = Self {
             position,
+            hash_history: hash_history.to_vec(),
             tt,
             node_count: 0,
             timer: Timer::new(max_duration),
             prev_pv: Vec::new(),
+            pv_table: [[Move::default(); MAX_PLY]; MAX_PLY],
+            pv_length: [0; MAX_PLY],
             history: [[0u32; 128]; 128],
-            killers: [[None; 2]; 64],
+            killers: [[None; 2]; MAX_PLY],
+            san_pv,
         };
         search.search(depth)
     }
 
     fn search(&mut self, depth: u32) -> (Vec<Move>, u64) {
         for d in 1..depth + 1 {
-            let mut pv: Vec<Move> = Vec::new();
             let alpha = -1000000;
             let beta = 1000000;
             let ply = 0;
             let follow_pv = true;
-            let value = self.alphabeta(alpha, beta, d, ply, &mut pv, follow_pv);
+            let value = self.alphabeta(alpha, beta, d, ply, follow_pv);
 
             if !self.timer.stopped {
-                self.prev_pv = pv.clone();
+                let pv = &self.pv_table[0][..self.pv_length[0]];
+                self.prev_pv = pv.to_vec();
                 let pv_string = pv
-                    .clone()
                     .iter()
-                    .map(get_move_string)
+                    .map(|move_| self.position.uci_move_string(move_))
                     .collect::<Vec<_>>()
                     .join(" ");
                 println!(
                     "info score cp {value} depth {d} nodes {} pv {pv_string}",
                     self.node_count
                 );
+                if self.san_pv {
+                    println!("info string pv {}", line_to_san(self.position, pv));
+                }
             }
         }
         (self.prev_pv.clone(), self.node_count)
     }
 
-    fn order_moves_inplace(&self, moves: &mut [Move], ply: u32, tt_move: Option<&Move>) {
-        let pv_move = self.prev_pv.get(ply as usize);
-        moveordering::order_moves_inplace(
-            self.position,
-            moves,
-            ply,
-            pv_move,
-            tt_move,
-            self.killers,
-            self.history,
-        );
-    }
-
-    fn quiescence(&mut self, mut alpha: i32, beta: i32, ply: u32) -> i32 {
+    // qply counts the plies since the main search ended, quiet checks are only tried at the first
+    fn quiescence(&mut self, mut alpha: i32, beta: i32, ply: u32, qply: u32) -> i32 {
         if self.timer.should_stop(self.node_count) {
             return 0;
         }
         let stand_pat = evaluate(self.position);
-
-        if stand_pat >= beta {
-            return beta; // fail hard beta-cutoff
-        }
-        if stand_pat > alpha {
-            alpha = stand_pat; // new lower bound -> pv move
+        if ply as usize >= MAX_PLY {
+            return stand_pat;
         }
 
-        let mut moves = self.position.generate_tactical_moves();
+        // no standing pat in check, every evasion is searched
+        let in_check = self.position.in_check();
+        let mut picker = if in_check {
+            MovePicker::new_evasions(None)
+        } else {
+            if stand_pat >= beta {
+                return beta; // fail hard beta-cutoff
+            }
+            if stand_pat > alpha {
+                alpha = stand_pat; // new lower bound -> pv move
+            }
+            if qply == 0 {
+                MovePicker::new_captures_and_checks()
+            } else {
+                MovePicker::new_captures()
+            }
+        };
 
-        // Move ordering
-        self.order_moves_inplace(&mut moves, ply, None);
-        for move_ in moves {
-            self.position.make_move(&move_, ply);
+        let mut legal_moves = 0;
+        while let Some(move_) = picker.next(self.position, &self.history) {
+            let undo = self.position.make_move(&move_);
+            legal_moves += 1;
             self.node_count += 1;
-            let value = -self.quiescence(-beta, -alpha, ply + 1);
-            self.position.unmake_move(&move_, ply);
+            let value = -self.quiescence(-beta, -alpha, ply + 1, qply + 1);
+            self.position.unmake_move(&move_, undo);
             if value >= beta {
                 return beta; // fail hard beta-cutoff
             }
@@ -138,6 +153,9 @@ impl<'a> Search<'a> {
                 alpha = value; // new lower bound -> pv move
             }
         }
+        if in_check && legal_moves == 0 {
+            return -MATE_SCORE;
+        }
         alpha
     }
 
@@ -147,27 +165,30 @@ impl<'a> Search<'a> {
         beta: i32,
         mut depth: u32,
         ply: u32,
-        pv: &mut Vec<Move>,
         pv_node: bool,
     ) -> i32 {
+        let ply_idx = ply as usize;
+        self.pv_length[ply_idx] = ply_idx;
+        if ply_idx >= MAX_PLY - 1 {
+            return evaluate(self.position);
+        }
         if ply > 0 && self.timer.should_stop(self.node_count) {
             return 0;
         }
 
-        if ply > 0 && self.position.is_repetition() {
-            return 0;
+        if ply > 0 && self.position.is_repetition(&self.hash_history) {
+            return DRAW_SCORE;
         }
         if self.position.fifty >= 100 {
-            return 0;
+            return DRAW_SCORE;
+        }
+        // a side with two knights can still be mated, so not while in check
+        let in_check = self.position.in_check();
+        if ply > 0 && !in_check && self.position.is_material_draw() {
+            return DRAW_SCORE;
         }
 
         // check extension
-        let idx = match self.position.is_white_turn {
-            true => 0,
-            false => 1,
-        };
-        let in_check = is_square_attacked(self.position.king_squares[idx], self.position);
-
         if in_check {
             depth += 1;
         }
@@ -177,8 +198,7 @@ impl<'a> Search<'a> {
             let copy_ep = self.position.enpassant_square;
             self.position.make_null();
 
-            let mut line = Vec::new();
-            let value = -self.alphabeta(-beta, -beta + 1, depth - 3, ply + 1, &mut line, false);
+            let value = -self.alphabeta(-beta, -beta + 1, depth - 3, ply + 1, false);
             self.position.unmake_null(copy_ep);
 
             if value >= beta {
@@ -189,106 +209,93 @@ impl<'a> Search<'a> {
         // leaf node
         if depth == 0 {
             // TODO: Maybe not pass history and killers to quiesc? maybe just sort using mvv lva in there?
-            return self.quiescence(alpha, beta, ply + 1);
+            return self.quiescence(alpha, beta, ply + 1, 0);
         }
 
-        let mut tt_move: Option<&Move> = None;
-        if ply > 0 {
-            let (tt_value, _tt_move) = self.tt.read_entry(self.position.hash, alpha, beta, depth);
-            if let Some(value) = tt_value {
-                return value;
-            }
-            tt_move = _tt_move;
+        let (tt_value, tt_move) = self.tt.read_entry(self.position.hash, alpha, beta, depth);
+        if ply > 0
+            && let Some(value) = tt_value
+        {
+            return value;
         }
+        // fall back to the previous iteration's PV when the TT has nothing
+        let tt_move = tt_move.copied().or(self.prev_pv.get(ply_idx).copied());
+        let killers = self.killers.get(ply_idx).copied().unwrap_or_default();
 
-        let mut moves = self.position.generate_pseudo_moves();
+        let mut picker = if in_check {
+            MovePicker::new_evasions(tt_move)
+        } else {
+            MovePicker::new(tt_move, killers)
+        };
         let mut node_type = NodeType::AlphaBound;
         let mut best_move: Option<Move> = None;
         let mut follow_pv = true;
         let mut legal_moves = 0;
-        // Move ordering
-        self.order_moves_inplace(&mut moves, ply, tt_move);
-        for move_ in moves {
-            self.position.make_move(&move_, ply);
-
-            if is_legal(self.position) {
-                legal_moves += 1;
-                // Local PV buffer for children
-                let mut line = Vec::new();
-                self.node_count += 1;
-                // do not store to first rep index
-                self.position.repetition_index += 1;
-                self.position.repetition_stack[self.position.repetition_index] = self.position.hash;
-                let mut value;
+        while let Some(move_) = picker.next(self.position, &self.history) {
+            self.hash_history.push(self.position.hash);
+            let undo = self.position.make_move(&move_);
+            legal_moves += 1;
+            self.node_count += 1;
+            let mut value;
 
-                // Principal variation search
-                if legal_moves == 1 {
-                    // Search PV move with full window
-                    value =
-                        -self.alphabeta(-beta, -alpha, depth - 1, ply + 1, &mut line, follow_pv);
-                } else {
-                    // Search other moves with null window
-                    value = -self.alphabeta(
-                        -alpha - 1,
-                        -alpha,
-                        depth - 1,
-                        ply + 1,
-                        &mut line,
-                        follow_pv,
-                    );
-                    if value > alpha && value < beta {
-                        // didn't stay inside the window
-                        // need to re-search with full window
-                        value = -self.alphabeta(
-                            -beta,
-                            -alpha,
-                            depth - 1,
-                            ply + 1,
-                            &mut line,
-                            follow_pv,
-                        );
-                    }
+            // Principal variation search
+            if legal_moves == 1 {
+                // Search PV move with full window
+                value = -self.alphabeta(-beta, -alpha, depth - 1, ply + 1, follow_pv);
+            } else {
+                // Search other moves with null window
+                value = -self.alphabeta(-alpha - 1, -alpha, depth - 1, ply + 1, follow_pv);
+                if value > alpha && value < beta {
+                    // didn't stay inside the window
+                    // need to re-search with full window
+                    value = -self.alphabeta(-beta, -alpha, depth - 1, ply + 1, follow_pv);
                 }
+            }
 
-                self.position.unmake_move(&move_, ply);
-                self.position.repetition_index -= 1;
-                follow_pv = false;
+            self.position.unmake_move(&move_, undo);
+            self.hash_history.pop();
+            follow_pv = false;
 
-                if value >= beta {
-                    if !move_.is_capture {
-                        self.history[move_.from][move_.to] += depth * depth;
-                        self.killers[ply as usize][1] = self.killers[ply as usize][0];
-                        self.killers[ply as usize][0] = Some(move_);
+            if value >= beta {
+                if !move_.is_capture() {
+                    self.history[move_.from().index()][move_.to().index()] += depth * depth;
+                    if let Some(ply_killers) = self.killers.get_mut(ply as usize)
+                        && ply_killers[0] != Some(move_)
+                    {
+                        ply_killers[1] = ply_killers[0];
+                        ply_killers[0] = Some(move_);
                     }
-                    self.tt.write_entry(
-                        self.position.hash,
-                        beta,
-                        NodeType::BetaBound,
-                        depth,
-                        Some(move_),
-                    );
-                    return beta; // fail hard beta-cutoff
                 }
-                if value > alpha {
-                    alpha = value; // new lower bound -> pv move
+                self.tt.write_entry(
+                    self.position.hash,
+                    beta,
+                    NodeType::BetaBound,
+                    depth,
+                    Some(move_),
+                );
+                return beta; // fail hard beta-cutoff
+            }
+            if value > alpha {
+                alpha = value; // new lower bound -> pv move
 
-                    // Update PV: prepend current move to child's PV
-                    pv.clear();
-                    pv.push(move_);
-                    pv.append(&mut line);
+                // Update PV: current move followed by the child's PV
+                let child_length = self.pv_length[ply_idx + 1];
+                let (parent_rows, child_rows) = self.pv_table.split_at_mut(ply_idx + 1);
+                let pv = &mut parent_rows[ply_idx];
+                pv[ply_idx] = move_;
+                pv[ply_idx + 1..child_length]
+                    .copy_from_slice(&child_rows[0][ply_idx + 1..child_length]);
+                self.pv_length[ply_idx] = child_length.max(ply_idx + 1);
 
-                    node_type = NodeType::Exact;
-                    best_move = Some(move_);
-                }
-            } else {
-                self.position.unmake_move(&move_, ply);
+                node_type = NodeType::Exact;
+                best_move = Some(move_);
             }
         }
         if legal_moves == 0 {
             if in_check {
-                return -50000;
+                return -MATE_SCORE;
             } else {
-                return 0;
+                return DRAW_SCORE;
             }
         }
         self.tt
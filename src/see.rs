@@ -0,0 +1,229 @@
+use crate::{
+    bitboard::{bishop_attacks, rook_attacks, square_bit},
+    evaluation::get_material_score,
+    movegen::{Move, attackers},
+    piece::{Color, PieceKind},
+    position::Position,
+};
+
+// the pieces still in an exchange on one square, shared by see and see_ge
+struct Exchange<'a> {
+    position: &'a Position,
+    to: usize,
+    occupancy: u64,
+    attackers: u64,
+    // sliders of both sides, queens are in both
+    bishops: u64,
+    rooks: u64,
+    // the side to take back next
+    side: Color,
+}
+
+impl<'a> Exchange<'a> {
+    // the exchange after move_, with the material the move itself wins and the
+    // piece it leaves on the target square, None for castling
+    fn start(position: &'a Position, move_: Move) -> Option<(Self, i32, PieceKind)> {
+        if move_.is_castling() {
+            return None;
+        }
+        let (from, to) = (move_.from().to_64(), move_.to().to_64());
+        let mover = position.moved_piece(&move_)?;
+
+        let mut occupancy = position.occupied() ^ square_bit(from);
+        let mut gain = 0;
+        if let Some(victim) = position.captured_piece(&move_) {
+            gain = get_material_score(victim.kind());
+        }
+        if move_.is_enpassant() {
+            let captured = match mover.color() {
+                Color::White => to + 8,
+                Color::Black => to - 8,
+            };
+            occupancy ^= square_bit(captured);
+        }
+        let mut on_square = mover.kind();
+        if let Some(promoted_piece) = move_.promoted_piece() {
+            gain += get_material_score(promoted_piece) - get_material_score(PieceKind::Pawn);
+            on_square = promoted_piece;
+        }
+
+        let bishops = position.pieces(Color::White, PieceKind::Bishop)
+            | position.pieces(Color::Black, PieceKind::Bishop)
+            | position.pieces(Color::White, PieceKind::Queen)
+            | position.pieces(Color::Black, PieceKind::Queen);
+        let rooks = position.pieces(Color::White, PieceKind::Rook)
+            | position.pieces(Color::Black, PieceKind::Rook)
+            | position.pieces(Color::White, PieceKind::Queen)
+            | position.pieces(Color::Black, PieceKind::Queen);
+        let attackers = (attackers(position, to, occupancy, Color::White)
+            | attackers(position, to, occupancy, Color::Black))
+            & occupancy;
+        let exchange = Exchange {
+            position,
+            to,
+            occupancy,
+            attackers,
+            bishops,
+            rooks,
+            side: !mover.color(),
+        };
+        Some((exchange, gain, on_square))
+    }
+
+    // takes back with the least valuable piece of the side to move, None if it
+    // has nothing that can
+    fn capture(&mut self) -> Option<PieceKind> {
+        let (side, position) = (self.side, self.position);
+        let side_attackers = self.attackers & position.occupancy[side.index()];
+        let (kind, attacker) = PieceKind::ALL.iter().find_map(|&kind| {
+            let pieces = side_attackers & position.pieces(side, kind);
+            (pieces != 0).then_some((kind, pieces & pieces.wrapping_neg()))
+        })?;
+        // the king can't take back onto a square the other side still covers
+        if kind == PieceKind::King && self.attackers & position.occupancy[(!side).index()] != 0 {
+            return None;
+        }
+
+        self.occupancy ^= attacker;
+        // sliders behind the piece that just took join in
+        self.attackers |= (bishop_attacks(self.to, self.occupancy) & self.bishops)
+            | (rook_attacks(self.to, self.occupancy) & self.rooks);
+        self.attackers &= self.occupancy;
+        self.side = !side;
+        Some(kind)
+    }
+}
+
+// Static exchange evaluation: the material won or lost by the side to move if
+// both sides keep recapturing on the target square with their least valuable
+// piece, each stopping as soon as going on would lose more. Sliders lined up
+// behind other attackers join in once the pieces in front are gone. Pins are
+// ignored. Quiet moves are scored as well, as the loss of the moved piece if
+// the square is not safe.
+pub fn see(position: &Position, move_: Move) -> i32 {
+    let Some((mut exchange, first_gain, mut on_square)) = Exchange::start(position, move_) else {
+        return 0;
+    };
+    let mut gain = [0; 32];
+    gain[0] = first_gain;
+    let mut depth = 0;
+    while depth < gain.len() - 1 {
+        let Some(kind) = exchange.capture() else {
+            break;
+        };
+        depth += 1;
+        gain[depth] = get_material_score(on_square) - gain[depth - 1];
+        on_square = kind;
+    }
+
+    // each side picks the better of standing pat and going on with the exchange
+    while depth > 0 {
+        gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+        depth -= 1;
+    }
+    gain[0]
+}
+
+// Whether see(position, move_) >= threshold, without working out the whole
+// exchange. balance is what the side that just took keeps above the threshold
+// if the other side takes back and the exchange then stops, once it isn't
+// negative the other side has lost.
+pub fn see_ge(position: &Position, move_: Move, threshold: i32) -> bool {
+    let Some((mut exchange, first_gain, on_square)) = Exchange::start(position, move_) else {
+        return threshold <= 0;
+    };
+    let mover = !exchange.side;
+    let mut balance = first_gain - threshold;
+    if balance < 0 {
+        return false;
+    }
+    balance -= get_material_score(on_square);
+    if balance >= 0 {
+        return true;
+    }
+    // the side that can't or won't take back next has lost
+    while let Some(kind) = exchange.capture() {
+        balance = -balance - 1 - get_material_score(kind);
+        if balance >= 0 {
+            break;
+        }
+    }
+    exchange.side != mover
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::square::Square;
+
+    fn find_move(position: &Position, move_string: &str) -> Move {
+        let from = Square::from_algebraic(&move_string[..2]).unwrap();
+        let to = Square::from_algebraic(&move_string[2..4]).unwrap();
+        position
+            .generate_legal_moves()
+            .iter()
+            .find(|move_| move_.from() == from && move_.to() == to)
+            .expect("move is legal")
+    }
+
+    fn see_of(fen: &str, move_string: &str) -> i32 {
+        let position = Position::from_fen(fen);
+        see(&position, find_move(&position, move_string))
+    }
+
+    #[test]
+    fn test_see_exchanges() {
+        // loose pawn
+        assert_eq!(
+            see_of("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"),
+            100
+        );
+        // queen takes a pawn defended by a pawn
+        assert_eq!(see_of("4k3/8/3p4/4p3/8/8/8/4QK2 w - - 0 1", "e1e5"), -900);
+        // knight for pawn, the rest of the exchange is worse for whoever goes on
+        let fen = "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1";
+        assert_eq!(see_of(fen, "d3e5"), -250);
+        // the rook behind the rook wins the pawn by x-ray
+        assert_eq!(see_of("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), 100);
+        // the king can't take back on a square the other rook still covers
+        assert_eq!(see_of("3r4/8/8/8/8/8/2k5/3RK3 b - - 0 1", "d8d1"), 525);
+        // moving a piece to an attacked square loses it
+        assert_eq!(see_of("4k3/8/8/2p5/8/8/8/2N1K3 w - - 0 1", "c1b3"), 0);
+        assert_eq!(see_of("4k3/8/8/2p5/8/8/8/2N1K3 w - - 0 1", "c1d3"), 0);
+        assert_eq!(see_of("4k3/8/8/8/2p5/8/8/2N1K3 w - - 0 1", "c1d3"), -350);
+    }
+
+    #[test]
+    fn test_see_ge_thresholds() {
+        let position = Position::from_fen("4k3/8/3p4/4p3/8/8/8/4QK2 w - - 0 1");
+        let move_ = find_move(&position, "e1e5");
+        assert!(see_ge(&position, move_, -900));
+        assert!(!see_ge(&position, move_, -899));
+        assert!(!see_ge(&position, move_, 0));
+    }
+
+    #[test]
+    fn test_see_ge_agrees_with_see() {
+        let fens = [
+            "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "3r4/8/8/8/8/8/2k5/3RK3 b - - 0 1",
+            "2r1r1k1/pp1q1ppp/2n1b3/3pP3/3Pn3/2NB1N2/PPQ2PPP/R3R1K1 w - - 0 1",
+            "4k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        ];
+        for fen in fens {
+            let position = Position::from_fen(fen);
+            for move_ in &position.generate_legal_moves() {
+                let value = see(&position, move_);
+                for threshold in (-1000..=1000).step_by(25).chain([value, value + 1]) {
+                    assert_eq!(
+                        see_ge(&position, move_, threshold),
+                        value >= threshold,
+                        "{fen} {} {threshold}",
+                        crate::movegen::get_move_string(&move_)
+                    );
+                }
+            }
+        }
+    }
+}
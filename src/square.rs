@@ -0,0 +1,114 @@
+use std::fmt;
+
+// A board square, stored as its 0x88 index: a8 is 0, h8 is 7, a1 is 112 and h1 is 119.
+// Bitboards use the 64-square index from to_64, where a8 is 0 and h1 is 63.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Square(u8);
+
+pub const BOARD_SQUARES: [Square; 64] = {
+    let mut squares = [Square(0); 64];
+    let mut i = 0;
+    while i < 64 {
+        squares[i] = Square::from_64(i);
+        i += 1;
+    }
+    squares
+};
+
+impl Square {
+    pub const fn from_index(index: usize) -> Square {
+        assert!(index & 0x88 == 0, "0x88 index is off board");
+        Square(index as u8)
+    }
+
+    pub const fn from_64(square_in_64: usize) -> Square {
+        assert!(square_in_64 < 64, "square index is off board");
+        Square((square_in_64 + (square_in_64 & !7)) as u8)
+    }
+
+    // file 0..7 is a..h and rank 0..7 is the first to the eighth rank
+    pub const fn from_file_rank(file: usize, rank: usize) -> Square {
+        assert!(file < 8 && rank < 8, "file or rank is off board");
+        Square(((7 - rank) * 16 + file) as u8)
+    }
+
+    pub fn from_algebraic(square: &str) -> Option<Square> {
+        let mut chars = square.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(file @ 'a'..='h'), Some(rank @ '1'..='8'), None) => Some(Square::from_file_rank(
+                file as usize - 'a' as usize,
+                rank as usize - '1' as usize,
+            )),
+            _ => None,
+        }
+    }
+
+    // 0x88 index, for the board array and per-square tables
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    // 64-square index, for bitboards
+    pub const fn to_64(self) -> usize {
+        (self.0 as usize + (self.0 as usize & 7)) >> 1
+    }
+
+    pub const fn file(self) -> usize {
+        self.0 as usize & 7
+    }
+
+    // 0 for the first rank up to 7 for the eighth, the board array runs the other way
+    pub const fn rank(self) -> usize {
+        7 - (self.0 as usize >> 4)
+    }
+
+    // the square delta away in 0x88 steps, 1 per file right and -16 per rank up,
+    // or None if that goes off the board
+    pub fn offset(self, delta: isize) -> Option<Square> {
+        match self.index().checked_add_signed(delta) {
+            Some(index) if index < 128 && index & 0x88 == 0 => Some(Square(index as u8)),
+            _ => None,
+        }
+    }
+
+    pub fn to_algebraic(self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let file_char = (b'a' + self.file() as u8) as char;
+        let rank_char = (b'1' + self.rank() as u8) as char;
+        write!(f, "{}{}", file_char, rank_char)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square_conversions() {
+        let e4 = Square::from_algebraic("e4").unwrap();
+        assert_eq!(e4.index(), 68);
+        assert_eq!(e4.to_64(), 36);
+        assert_eq!(Square::from_64(36), e4);
+        assert_eq!((e4.file(), e4.rank()), (4, 3));
+        assert_eq!(Square::from_file_rank(4, 3), e4);
+        assert_eq!(e4.to_algebraic(), "e4");
+        assert_eq!(e4.offset(-16), Square::from_algebraic("e5"));
+        assert_eq!(Square::from_algebraic("h1").unwrap().offset(1), None);
+        // far off the board either way, rather than wrapping back onto it
+        let a8 = Square::from_algebraic("a8").unwrap();
+        assert_eq!(a8.offset(-16), None);
+        assert_eq!(a8.offset(256), None);
+        assert_eq!(a8.offset(-256), None);
+        assert_eq!(a8.offset(isize::MIN), None);
+        assert_eq!(a8.offset(119), Square::from_algebraic("h1"));
+        assert_eq!(Square::from_algebraic("i1"), None);
+        assert_eq!(Square::from_algebraic("a9"), None);
+        assert_eq!(BOARD_SQUARES[0].to_algebraic(), "a8");
+        assert_eq!(BOARD_SQUARES[63].to_algebraic(), "h1");
+    }
+}
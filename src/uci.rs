@@ -3,13 +3,8 @@ use std::{io, time::Instant};
 use regex::Regex;
 
 use crate::{
-    START_POSITION_FEN,
-    hash::TranspositionTable,
-    movegen::{Move, get_move_string},
-    perft::run_perft,
-    piece::{BISHOP, BLACK, KNIGHT, QUEEN, ROOK, WHITE},
-    position::Position,
-    search::Search,
+    START_POSITION_FEN, hash::TranspositionTable, movegen::Move, perft::run_perft, piece::Color,
+    position::Position, san::MoveParseError, search::Search,
 };
 
 fn read_line() -> String {
@@ -20,70 +15,63 @@ fn read_line() -> String {
     }
 }
 
-fn parse_move(move_string: &str, position: &mut Position) -> Move {
-    // e2e4 e7e5 g1f3 b8c6 f1b5 c2c1q
-    let from_file = move_string.chars().next().unwrap() as usize;
-    let from_rank = move_string.chars().nth(1).unwrap().to_digit(10).unwrap() as usize;
-    let to_file = move_string.chars().nth(2).unwrap() as usize;
-    let to_rank = move_string.chars().nth(3).unwrap().to_digit(10).unwrap() as usize;
-
-    let prom_piece_str = move_string.chars().nth(4);
-    let piece_color = match position.is_white_turn {
-        true => WHITE,
-        false => BLACK,
-    };
-    let prom_piece = match prom_piece_str {
-        Some('n') => Some(KNIGHT | piece_color),
-        Some('b') => Some(BISHOP | piece_color),
-        Some('r') => Some(ROOK | piece_color),
-        Some('q') => Some(QUEEN | piece_color),
-        Some(_other) => None,
-        None => None,
-    };
-
-    let from_square = (from_file - 97) + ((8 - from_rank) * 16);
-    let to_square = (to_file - 97) + ((8 - to_rank) * 16);
-
+fn parse_move(move_string: &str, position: &Position) -> Result<Move, MoveParseError> {
+    // e2e4 e7e5 g1f3 b8c6 f1b5 c2c1q, castling as e1h1 with UCI_Chess960
     let moves = position.generate_legal_moves();
-    for move_ in moves {
-        if move_.from == from_square && move_.to == to_square && move_.promoted_piece == prom_piece
-        {
-            return move_;
+    for move_ in &moves {
+        if position.uci_move_string(&move_) == move_string {
+            return Ok(move_);
         }
     }
-    panic!(
-        "Parsed move is not matched to any legal move: from: {} to: {}",
-        from_square, to_square
-    );
+    Err(MoveParseError::Illegal(move_string.to_string()))
 }
 
-pub fn handle_position(input: &str, position: &mut Position) {
+pub fn handle_position(input: &str, position: &mut Position, hash_history: &mut Vec<u64>) {
     // > position startpos
     // > position startpos moves e2e4 e7e5 g1f3 b8c6 f1b5
     // > position fen 8/1B6/8/5p2/8/8/5Qrq/1K1R2bk w - - 0 1
     // > position fen 8/3P3k/n2K3p/2p3n1/1b4N1/2p1p1P1/8/3B4 w - - 0 1 moves g4f6 h7g7 f6h5 g7g6 d1c2
+    let chess960 = position.chess960;
     if input.contains("fen") {
         let fen_part = input.strip_prefix("position fen ").unwrap();
-        *position = Position::from_fen(fen_part);
+        let fen_part = fen_part.split(" moves").next().unwrap();
+        match Position::try_from_fen(fen_part) {
+            Ok(new_position) => *position = new_position,
+            Err(error) => {
+                println!("info string invalid fen: {error}");
+                return;
+            }
+        }
     } else if input.contains("startpos") {
         *position = Position::from_fen(START_POSITION_FEN);
     }
-    position.repetition_index += 1;
-    position.repetition_stack[position.repetition_index] = position.hash;
+    position.chess960 = chess960;
+    hash_history.clear();
     if input.contains("moves") {
         let index = input.find("moves").unwrap();
         let moves_part = &input[index + 6..];
-        for move_string in moves_part.split(" ") {
-            let move_ = parse_move(move_string, position);
-            position.make_move(&move_, 0);
-            position.repetition_index += 1;
-            position.repetition_stack[position.repetition_index] = position.hash;
+        for move_string in moves_part.split_ascii_whitespace() {
+            let move_ = match parse_move(move_string, position) {
+                Ok(move_) => move_,
+                Err(error) => {
+                    println!("info string {error}");
+                    return;
+                }
+            };
+            hash_history.push(position.hash);
+            position.make_move(&move_);
         }
         //println!("{moves_part}");
     }
 }
 
-fn handle_go(input: &str, position: &mut Position, tt: &mut TranspositionTable) {
+fn handle_go(
+    input: &str,
+    position: &mut Position,
+    hash_history: &[u64],
+    tt: &mut TranspositionTable,
+    san_pv: bool,
+) {
     // default depth
     let mut depth: u32 = 64;
 
@@ -96,7 +84,7 @@ fn handle_go(input: &str, position: &mut Position, tt: &mut TranspositionTable)
         depth = input[index + 6..].trim().parse::<u32>().unwrap();
         println!("depth: {depth}");
     }
-    if input.contains("wtime") && position.is_white_turn {
+    if input.contains("wtime") && position.side_to_move == Color::White {
         let re = Regex::new(r"[\s\S]+wtime (\d+)").unwrap();
         let Some(caps) = re.captures(input) else {
             return;
@@ -104,7 +92,7 @@ fn handle_go(input: &str, position: &mut Position, tt: &mut TranspositionTable)
         base = caps[1].parse::<u64>().unwrap();
         println!("wtime: {base}");
     }
-    if input.contains("winc") && position.is_white_turn {
+    if input.contains("winc") && position.side_to_move == Color::White {
         let re = Regex::new(r"[\s\S]+winc (\d+)").unwrap();
         let Some(caps) = re.captures(input) else {
             return;
@@ -112,7 +100,7 @@ fn handle_go(input: &str, position: &mut Position, tt: &mut TranspositionTable)
         increment = caps[1].parse::<u64>().unwrap();
         println!("winc: {increment}");
     }
-    if input.contains("btime") && !position.is_white_turn {
+    if input.contains("btime") && position.side_to_move == Color::Black {
         let re = Regex::new(r"[\s\S]+btime (\d+)").unwrap();
         let Some(caps) = re.captures(input) else {
             return;
@@ -120,7 +108,7 @@ fn handle_go(input: &str, position: &mut Position, tt: &mut TranspositionTable)
         base = caps[1].parse::<u64>().unwrap();
         println!("btime: {base}");
     }
-    if input.contains("binc") && !position.is_white_turn {
+    if input.contains("binc") && position.side_to_move == Color::Black {
         let re = Regex::new(r"[\s\S]+binc (\d+)").unwrap();
         let Some(caps) = re.captures(input) else {
             return;
@@ -141,14 +129,14 @@ fn handle_go(input: &str, position: &mut Position, tt: &mut TranspositionTable)
     );
 
     let start = Instant::now();
-    let (pv, node_count) = Search::run(position, tt, depth, movetime);
+    let (pv, node_count) = Search::run(position, hash_history, tt, depth, movetime, san_pv);
     let duration = start.elapsed().as_secs_f32();
     let nodes_per_sec = (node_count as f32 / duration) as u64;
     let best_move = pv.first().expect("pv should have moves");
 
     println!("info nodes {}", node_count);
     println!("info nps {}", nodes_per_sec);
-    println!("bestmove {}", get_move_string(best_move));
+    println!("bestmove {}", position.uci_move_string(best_move));
 }
 
 // Minimum UCI Requirements
@@ -167,23 +155,38 @@ fn handle_go(input: &str, position: &mut Position, tt: &mut TranspositionTable)
 
 pub fn uci_loop() {
     let mut position = Position::from_fen(START_POSITION_FEN);
+    // hashes of the positions played before the current one, for repetitions
+    let mut hash_history = Vec::new();
     let mut tt = TranspositionTable::new(64);
+    let mut san_pv = false;
 
     loop {
         let input = read_line();
 
         if input.contains("position") {
-            handle_position(&input, &mut position);
+            handle_position(&input, &mut position, &mut hash_history);
             position.print();
+        } else if input.contains("setoption") {
+            // > setoption name UCI_Chess960 value true
+            if input.contains("UCI_Chess960") {
+                position.chess960 = input.contains("value true");
+            }
+            // > setoption name SanPv value true
+            if input.contains("SanPv") {
+                san_pv = input.contains("value true");
+            }
         } else if input.contains("quit") {
             break;
         } else if input.contains("ucinewgame") {
+            let chess960 = position.chess960;
             position = Position::from_fen(START_POSITION_FEN);
+            position.chess960 = chess960;
+            hash_history.clear();
             position.print();
         } else if input.contains("isready") {
             println!("readyok");
         } else if input.contains("go") {
-            handle_go(&input, &mut position, &mut tt);
+            handle_go(&input, &mut position, &hash_history, &mut tt, san_pv);
         } else if input.contains("perft") {
             // use like: perft 5
             let depth = input[6..].trim().parse::<u32>().unwrap();
@@ -194,6 +197,8 @@ pub fn uci_loop() {
         } else if input.contains("uci") {
             println!("id name rustchess");
             println!("id author Eetu Rantala");
+            println!("option name UCI_Chess960 type check default false");
+            println!("option name SanPv type check default false");
             println!("uciok");
         }
     }
@@ -0,0 +1,49 @@
+use rustchess::{
+    bitboard::square_bit,
+    piece::Color,
+    position::Position,
+    square::{BOARD_SQUARES, Square},
+};
+
+fn bits(squares: &[&str]) -> u64 {
+    squares.iter().fold(0, |bits, name| {
+        bits | square_bit(Square::from_algebraic(name).unwrap().to_64())
+    })
+}
+
+#[test]
+fn test_attackers_and_checkers() {
+    // white king on e1 in check from the rook on e8 and the knight on d3
+    let position = Position::from_fen("4r1k1/8/8/8/8/3n4/2B5/R3K3 w - - 0 1");
+    assert_eq!(position.checkers(), bits(&["e8", "d3"]));
+    assert!(position.in_check());
+
+    let d3 = Square::from_algebraic("d3").unwrap();
+    assert_eq!(position.attackers_to(d3, Color::White), bits(&["c2"]));
+    assert_eq!(position.attackers_to(d3, Color::Black), 0);
+    let e5 = Square::from_algebraic("e5").unwrap();
+    assert_eq!(position.attackers_to(e5, Color::Black), bits(&["e8", "d3"]));
+}
+
+#[test]
+fn test_pinned_pieces() {
+    // the knight is pinned by the bishop and the rook by the queen, the bishop
+    // and knight on the first rank shield the king from the rook together
+    let position = Position::from_fen("4q1k1/8/8/b7/8/2N5/4R3/4KBNr w - - 0 1");
+    assert_eq!(position.pinned(Color::White), bits(&["c3", "e2"]));
+    assert_eq!(position.pinned(Color::Black), 0);
+    assert_eq!(position.checkers(), 0);
+}
+
+#[test]
+fn test_attack_map_matches_attackers_to() {
+    let position =
+        Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+    for color in [Color::White, Color::Black] {
+        let expected = BOARD_SQUARES
+            .iter()
+            .filter(|&&square| position.attackers_to(square, color) != 0)
+            .fold(0, |bits, square| bits | square_bit(square.to_64()));
+        assert_eq!(position.attack_map(color), expected);
+    }
+}
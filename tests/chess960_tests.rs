@@ -0,0 +1,75 @@
+use std::collections::HashSet;
+
+use rustchess::{
+    START_POSITION_FEN,
+    chess960::chess960_start_fen,
+    perft::{PerftCounts, perft},
+    position::Position,
+    uci::handle_position,
+};
+
+#[test]
+fn test_chess960_start_positions() {
+    assert_eq!(chess960_start_fen(518).unwrap(), START_POSITION_FEN);
+    assert_eq!(
+        chess960_start_fen(0).unwrap(),
+        "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+    );
+    assert_eq!(chess960_start_fen(960), None);
+
+    let mut fens = HashSet::new();
+    for index in 0..960 {
+        let fen = chess960_start_fen(index).unwrap();
+        let position = Position::from_fen(&fen);
+        assert_eq!(position.to_fen(), fen);
+        fens.insert(fen);
+    }
+    assert_eq!(fens.len(), 960);
+}
+
+#[test]
+fn test_chess960_castling_fen() {
+    // Shredder-FEN files are written back as X-FEN
+    let position =
+        Position::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9");
+    assert_eq!(
+        position.to_fen(),
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9"
+    );
+    // inner rook needs its file
+    let fen = "rn2k1r1/ppp1pp1p/3p2p1/5bn1/P7/2N2B2/1PPPPP2/2BNK1RR w Gkq - 4 11";
+    assert_eq!(Position::from_fen(fen).to_fen(), fen);
+}
+
+#[test]
+fn test_chess960_castling_moves() {
+    // kings already on their castling squares, only the rooks move
+    let mut position = Position::from_fen("1r4kr/8/8/8/8/8/8/1R4KR w KQkq - 0 1");
+    position.chess960 = true;
+    handle_position(
+        "position fen 1r4kr/8/8/8/8/8/8/1R4KR w KQkq - 0 1 moves g1h1 g8h8",
+        &mut position,
+        &mut Vec::new(),
+    );
+    assert_eq!(position.to_fen(), "1r3rk1/8/8/8/8/8/8/1R3RK1 w - - 2 2");
+
+    let expected = Position::from_fen("1r3rk1/8/8/8/8/8/8/1R3RK1 w - - 2 2");
+    assert_eq!(position.hash, expected.hash);
+
+    let mut counts = PerftCounts::default();
+    let mut start = Position::from_fen(&chess960_start_fen(0).unwrap());
+    assert_eq!(perft(2, &mut start, &mut counts, false), 400);
+}
+
+#[test]
+fn test_castling_rook_shielding_the_king() {
+    // castling b1 would leave the king on c1 facing the queen on a1
+    let position = Position::from_fen("4k3/8/8/8/8/8/8/qRK5 w B - 0 1");
+    let moves = position.generate_legal_moves();
+    assert!(!moves.iter().any(|move_| move_.is_castling()));
+
+    // without the queen behind the rook castling is fine
+    let position = Position::from_fen("4k3/8/8/8/8/8/8/1RK5 w B - 0 1");
+    let moves = position.generate_legal_moves();
+    assert!(moves.iter().any(|move_| move_.is_castling()));
+}
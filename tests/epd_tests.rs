@@ -0,0 +1,97 @@
+use rustchess::{
+    epd::{Epd, EpdError},
+    movegen::get_chess960_move_string,
+    position::Position,
+    san::MoveParseError,
+};
+
+fn move_strings(moves: &[rustchess::movegen::Move]) -> Vec<String> {
+    moves.iter().map(get_chess960_move_string).collect()
+}
+
+#[test]
+fn test_parse_operations() {
+    let epd = Epd::parse(
+        "r1bq2rk/pp3pbp/2p1p1pQ/7P/3P4/2PB1N2/PP3PPR/2KR4 w - - bm Qxh7+ Bxg6; am Qxg7+; \
+         id \"WAC.004\"; c0 \"mate; in a few\"; c9 \"x\"; ce +320; acd 12; \
+         pv Qxh7+ Kxh7 hxg6+; dm 3; sv Qxh7+",
+    )
+    .unwrap();
+    assert_eq!(
+        epd.position.to_fen(),
+        "r1bq2rk/pp3pbp/2p1p1pQ/7P/3P4/2PB1N2/PP3PPR/2KR4 w - - 0 1"
+    );
+    assert_eq!(move_strings(&epd.best_moves), ["h6h7", "d3g6"]);
+    assert_eq!(move_strings(&epd.avoid_moves), ["h6g7"]);
+    assert_eq!(epd.id.as_deref(), Some("WAC.004"));
+    assert_eq!(epd.comments[0].as_deref(), Some("mate; in a few"));
+    assert_eq!(epd.comments[9].as_deref(), Some("x"));
+    assert!(epd.comments[1..9].iter().all(Option::is_none));
+    assert_eq!(epd.eval, Some(320));
+    assert_eq!(epd.depth, Some(12));
+    assert_eq!(move_strings(&epd.pv), ["h6h7", "h8h7", "h5g6"]);
+    assert_eq!(epd.mate, Some(3));
+    assert_eq!(epd.other, [("sv".to_string(), "Qxh7+".to_string())]);
+}
+
+#[test]
+fn test_move_counters_and_bare_positions() {
+    let epd = Epd::parse("4k3/8/8/8/8/8/4P3/4K3 b - - hmvc 7; fmvn 42;").unwrap();
+    assert_eq!(epd.position.to_fen(), "4k3/8/8/8/8/8/4P3/4K3 b - - 7 42");
+    assert_eq!(epd.to_epd(), "4k3/8/8/8/8/8/4P3/4K3 b - - hmvc 7; fmvn 42;");
+
+    let epd = Epd::parse("  4k3/8/8/8/8/8/4P3/4K3 w - -  ").unwrap();
+    assert!(epd.best_moves.is_empty() && epd.other.is_empty());
+    assert_eq!(epd.to_epd(), "4k3/8/8/8/8/8/4P3/4K3 w - -");
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(
+        Epd::parse("4k3/8/8/8/8/8/4P3/4K3 w -").err(),
+        Some(EpdError::MissingFields(3))
+    );
+    assert!(matches!(
+        Epd::parse("4k3/8/8/8/8/8/4P3/4KK2 w - -"),
+        Err(EpdError::InvalidFen(_))
+    ));
+    assert_eq!(
+        Epd::parse("4k3/8/8/8/8/8/4P3/4K3 w - - bm e5;").err(),
+        Some(EpdError::InvalidMove(MoveParseError::Illegal(
+            "e5".to_string()
+        )))
+    );
+    assert_eq!(
+        Epd::parse("4k3/8/8/8/8/8/4P3/4K3 w - - acd deep;").err(),
+        Some(EpdError::InvalidOperation("acd deep".to_string()))
+    );
+    assert_eq!(
+        Epd::parse("4k3/8/8/8/8/8/4P3/4K3 w - - id \"open;").err(),
+        Some(EpdError::UnterminatedString)
+    );
+}
+
+#[test]
+fn test_write_analysis() {
+    let position = Position::from_fen("r1bq2rk/pp3pbp/2p1p1pQ/7P/3P4/2PB1N2/PP3PPR/2KR4 w - - 0 1");
+    let mut epd = Epd::new(position);
+    epd.pv = Epd::parse(&format!("{} pv Qxh7+ Kxh7 hxg6+", epd.to_epd()))
+        .unwrap()
+        .pv;
+    epd.best_moves = vec![epd.pv[0]];
+    epd.eval = Some(-15);
+    epd.depth = Some(9);
+    epd.id = Some("analysis 1".to_string());
+    epd.comments[2] = Some("rustchess".to_string());
+
+    let line = epd.to_epd();
+    assert_eq!(
+        line,
+        "r1bq2rk/pp3pbp/2p1p1pQ/7P/3P4/2PB1N2/PP3PPR/2KR4 w - - bm Qxh7+; ce -15; acd 9; \
+         pv Qxh7+ Kxh7 hxg6#; id \"analysis 1\"; c2 \"rustchess\";"
+    );
+    let read_back = Epd::parse(&line).unwrap();
+    assert_eq!(read_back.best_moves, epd.best_moves);
+    assert_eq!(read_back.pv, epd.pv);
+    assert_eq!(read_back.to_epd(), line);
+}
@@ -0,0 +1,96 @@
+use rustchess::{
+    START_POSITION_FEN,
+    piece::Color,
+    position::{FenError, Position},
+};
+
+#[test]
+fn test_fen_round_trip() {
+    let fens = [
+        START_POSITION_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        "rn1qkb1r/pp2pppp/5n2/1Ppp1b2/3P4/8/P1PNPPPP/R1BQKBNR w KQkq c6 0 5",
+        "8/8/8/3k4/8/6p1/7q/7K w - - 2 77",
+        "4k2r/8/8/8/8/8/8/5K2 b k - 0 1",
+    ];
+
+    for fen in fens {
+        let position = Position::from_fen(fen);
+        assert_eq!(position.to_fen(), fen);
+
+        let round_trip = Position::from_fen(&position.to_fen());
+        assert_eq!(round_trip.to_fen(), fen);
+        assert_eq!(round_trip.hash, position.hash, "{}", fen);
+    }
+}
+
+#[test]
+fn test_fen_optional_counters() {
+    let position = Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -");
+    assert_eq!(
+        position.to_fen(),
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"
+    );
+}
+
+#[test]
+fn test_fen_errors() {
+    let invalid_fens = [
+        ("", FenError::MissingFields(0)),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+            FenError::WrongRankCount(7),
+        ),
+        (
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            FenError::InvalidPiece('9'),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/4P4/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+            FenError::TooManySquares(4),
+        ),
+        (
+            "rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            FenError::TooFewSquares(7),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1",
+            FenError::InvalidPiece('X'),
+        ),
+        (
+            "rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1",
+            FenError::MissingKing(Color::Black),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+            FenError::InvalidSideToMove("x".to_string()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1",
+            FenError::InvalidCastling("KQkx".to_string()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e4 0 1",
+            FenError::InvalidEnPassant("e4".to_string()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1",
+            FenError::InvalidHalfmoveClock("x".to_string()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0",
+            FenError::InvalidFullmoveNumber("0".to_string()),
+        ),
+    ];
+
+    for (fen, expected_error) in invalid_fens {
+        assert_eq!(
+            Position::try_from_fen(fen).err(),
+            Some(expected_error),
+            "{}",
+            fen
+        );
+    }
+}
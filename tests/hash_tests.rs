@@ -1,4 +1,9 @@
-use rustchess::{START_POSITION_FEN, position::Position, uci::handle_position};
+use rustchess::{
+    START_POSITION_FEN,
+    hash::{POLYGLOT_RANDOM64, ZOBRIST_KEYS, ZobristKeys},
+    position::Position,
+    uci::handle_position,
+};
 
 #[test]
 fn test_incremental_hash_changes() {
@@ -45,7 +50,7 @@ fn test_incremental_hash_changes() {
         // Build position incrementally by making moves
         let mut incremental_pos = Position::from_fen(start_fen);
         let position_command = format!("position fen {} moves {}", start_fen, moves);
-        handle_position(&position_command, &mut incremental_pos);
+        handle_position(&position_command, &mut incremental_pos, &mut Vec::new());
 
         // Build position directly from final FEN
         let direct_pos = Position::from_fen(expected_fen);
@@ -56,5 +61,89 @@ fn test_incremental_hash_changes() {
             "Hash mismatch for moves: {} (starting from: {})",
             moves, start_fen
         );
+        assert_eq!(incremental_pos.to_fen(), expected_fen);
     }
 }
+
+#[test]
+fn test_unmake_restores_position_past_64_plies() {
+    let mut position = Position::from_fen(START_POSITION_FEN);
+    let start_fen = position.to_fen();
+    let start_hash = position.hash;
+
+    // knights shuffle back and forth, far deeper than any fixed ply table
+    let mut line = Vec::new();
+    for ply in 0..200 {
+        let move_string = ["g1f3", "g8f6", "f3g1", "f6g8"][ply % 4];
+        let move_ = position
+            .generate_legal_moves()
+            .into_iter()
+            .find(|move_| position.uci_move_string(move_) == move_string)
+            .unwrap();
+        let undo = position.make_move(&move_);
+        line.push((move_, undo));
+    }
+    assert_eq!(position.fifty, 200);
+
+    while let Some((move_, undo)) = line.pop() {
+        position.unmake_move(&move_, undo);
+    }
+    assert_eq!(position.to_fen(), start_fen);
+    assert_eq!(position.hash, start_hash);
+}
+
+#[test]
+fn test_copied_position_hashes_independently() {
+    let position = Position::from_fen(START_POSITION_FEN);
+    let mut copy = position;
+    let move_ = copy.generate_legal_moves()[0];
+    copy.make_move(&move_);
+
+    assert_eq!(position.to_fen(), START_POSITION_FEN);
+    assert_ne!(copy.hash, position.hash);
+    assert_eq!(copy.hash, Position::from_fen(&copy.to_fen()).hash);
+}
+
+#[test]
+fn test_enpassant_only_hashed_when_capturable() {
+    // nothing can take on e3, so the square doesn't change the hash
+    let with_square = Position::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1");
+    let without = Position::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1");
+    assert_eq!(with_square.hash, without.hash);
+
+    let with_square = Position::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1");
+    let without = Position::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1");
+    assert_ne!(with_square.hash, without.hash);
+    assert_eq!(with_square.hash, with_square.hash_with(&ZOBRIST_KEYS));
+}
+
+// the reference keys from the Polyglot book format description
+#[test]
+fn test_polyglot_keys() {
+    let expected = [
+        ("", 0x463b96181691fc9c_u64),
+        ("e2e4", 0x823c9b50fd114196),
+        ("e2e4 d7d5", 0x0756b94461c50fb0),
+        ("e2e4 d7d5 e4e5", 0x662fafb965db29d4),
+        ("e2e4 d7d5 e4e5 f7f5", 0x22a48b5a8e47ff78),
+        ("e2e4 d7d5 e4e5 f7f5 e1e2", 0x652a607ca3f242c1),
+        ("e2e4 d7d5 e4e5 f7f5 e1e2 e8f7", 0x00fdd303c946bdd9),
+        ("a2a4 b7b5 h2h4 b5b4 c2c4", 0x3c8123ea7b067637),
+        ("a2a4 b7b5 h2h4 b5b4 c2c4 b4c3 a1a3", 0x5c3f9b829b279560),
+    ];
+    for (moves, key) in expected {
+        let mut position = Position::from_fen(START_POSITION_FEN);
+        handle_position(
+            &format!("position startpos moves {moves}"),
+            &mut position,
+            &mut Vec::new(),
+        );
+        assert_eq!(position.hash, key, "{moves}");
+        // the same key from scratch as from the moves
+        assert_eq!(Position::from_fen(&position.to_fen()).hash, key, "{moves}");
+    }
+    assert_eq!(
+        ZobristKeys::from_random64(&POLYGLOT_RANDOM64).white_to_move_key,
+        ZOBRIST_KEYS.white_to_move_key
+    );
+}
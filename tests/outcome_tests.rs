@@ -0,0 +1,107 @@
+use rustchess::{
+    START_POSITION_FEN,
+    movegen::get_move_string,
+    piece::Color,
+    position::{Outcome, Position, Termination},
+};
+
+// plays the moves, keeping the hashes of the earlier positions like the uci loop does
+fn play(position: &mut Position, hash_history: &mut Vec<u64>, moves: &[&str]) {
+    for move_string in moves {
+        let move_ = position
+            .generate_legal_moves()
+            .iter()
+            .find(|move_| get_move_string(move_) == *move_string)
+            .expect("move is legal");
+        hash_history.push(position.hash);
+        position.make_move(&move_);
+    }
+}
+
+fn draw(termination: Termination) -> Option<Outcome> {
+    Some(Outcome {
+        winner: None,
+        termination,
+    })
+}
+
+#[test]
+fn test_checkmate_and_stalemate() {
+    let mut position = Position::from_fen(START_POSITION_FEN);
+    assert_eq!(position.outcome(&[]), None);
+    play(
+        &mut position,
+        &mut Vec::new(),
+        &["f2f3", "e7e5", "g2g4", "d8h4"],
+    );
+    assert_eq!(
+        position.outcome(&[]),
+        Some(Outcome {
+            winner: Some(Color::Black),
+            termination: Termination::Checkmate,
+        })
+    );
+
+    let position = Position::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+    assert_eq!(position.outcome(&[]), draw(Termination::Stalemate));
+}
+
+#[test]
+fn test_fifty_moves_and_threefold() {
+    let position = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 100 80");
+    assert_eq!(position.outcome(&[]), draw(Termination::FiftyMoves));
+    // mate on the hundredth half move still counts as mate
+    let position = Position::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 100 80");
+    assert_eq!(
+        position.outcome(&[]).map(|outcome| outcome.termination),
+        Some(Termination::Checkmate)
+    );
+
+    let mut position = Position::from_fen(START_POSITION_FEN);
+    let mut hash_history = Vec::new();
+    let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+    play(&mut position, &mut hash_history, &shuffle);
+    assert_eq!(position.repetition_count(&hash_history), 2);
+    assert_eq!(position.outcome(&hash_history), None);
+    play(&mut position, &mut hash_history, &shuffle);
+    assert_eq!(position.repetition_count(&hash_history), 3);
+    assert_eq!(
+        position.outcome(&hash_history),
+        draw(Termination::ThreefoldRepetition)
+    );
+}
+
+#[test]
+fn test_insufficient_material() {
+    let dead = [
+        "8/8/4k3/8/8/3K4/8/8 w - - 0 1",
+        "8/8/4k3/8/8/3KN3/8/8 w - - 0 1",
+        "8/8/4k3/8/8/3KB3/8/8 b - - 0 1",
+        // bishops on the same color, however many
+        "8/2b5/4k3/8/8/3K4/8/B7 w - - 0 1",
+        "8/2b1b3/4k3/8/8/3K4/8/B7 w - - 0 1",
+    ];
+    for fen in dead {
+        let position = Position::from_fen(fen);
+        assert!(position.is_insufficient_material(), "{fen}");
+        assert_eq!(
+            position.outcome(&[]),
+            draw(Termination::InsufficientMaterial),
+            "{fen}"
+        );
+    }
+
+    let alive = [
+        "8/8/4k3/8/8/3KNN2/8/8 w - - 0 1",
+        "8/3b4/4k3/8/8/3K4/8/B7 w - - 0 1",
+        "8/8/4k3/8/8/3KB3/8/6n1 w - - 0 1",
+        "8/8/4k3/8/8/3K4/7P/8 w - - 0 1",
+    ];
+    for fen in alive {
+        let position = Position::from_fen(fen);
+        assert!(!position.is_insufficient_material(), "{fen}");
+        // two knights can't force mate, but a helpmate is still possible
+        assert_eq!(position.is_material_draw(), fen.contains("NN"), "{fen}");
+        assert_eq!(position.outcome(&[]), None, "{fen}");
+    }
+}
@@ -1,4 +1,6 @@
 use rustchess::{
+    bitboard::square_bit,
+    movegen::{get_castling_targets, get_move_string},
     perft::{PerftCounts, perft},
     position::Position,
 };
@@ -281,3 +283,264 @@ fn perft_suite() {
         assert_eq!(total_nodes, test_pos.nodes, "{}", test_pos.fen_string)
     }
 }
+
+// Chess960 positions, castling rights in Shredder-FEN and X-FEN
+const CHESS960_1: PerftPosition = PerftPosition {
+    fen_string: "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+    depth: 4,
+    nodes: 326672,
+};
+const CHESS960_2: PerftPosition = PerftPosition {
+    fen_string: "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+    depth: 4,
+    nodes: 667366,
+};
+const CHESS960_3: PerftPosition = PerftPosition {
+    fen_string: "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+    depth: 4,
+    nodes: 273318,
+};
+const CHESS960_4: PerftPosition = PerftPosition {
+    fen_string: "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+    depth: 4,
+    nodes: 382958,
+};
+const CHESS960_5: PerftPosition = PerftPosition {
+    fen_string: "1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9",
+    depth: 4,
+    nodes: 1171749,
+};
+const CHESS960_6: PerftPosition = PerftPosition {
+    fen_string: "rn2k1r1/ppp1pp1p/3p2p1/5bn1/P7/2N2B2/1PPPPP2/2BNK1RR w Gkq - 4 11",
+    depth: 4,
+    nodes: 1111449,
+};
+
+#[test]
+fn perft_chess960_suite() {
+    let test_positions = [
+        CHESS960_1, CHESS960_2, CHESS960_3, CHESS960_4, CHESS960_5, CHESS960_6,
+    ];
+    for test_pos in test_positions {
+        let mut position = Position::from_fen(test_pos.fen_string);
+        let mut counts = PerftCounts::default();
+        let total_nodes = perft(test_pos.depth, &mut position, &mut counts, false);
+        assert_eq!(total_nodes, test_pos.nodes, "{}", test_pos.fen_string)
+    }
+}
+
+// calls visit on every position of the tree down to depth
+fn walk_tree(depth: u32, position: &mut Position, visit: &mut impl FnMut(&mut Position)) {
+    visit(position);
+    if depth > 0 {
+        for move_ in &position.generate_legal_moves() {
+            let undo = position.make_move(&move_);
+            walk_tree(depth - 1, position, visit);
+            position.unmake_move(&move_, undo);
+        }
+    }
+}
+
+#[test]
+fn evasions_match_legal_moves_in_check() {
+    let test_positions = [
+        (KIWIPETE.fen_string, 3),
+        (POSITION_3.fen_string, 4),
+        (POSITION_4.fen_string, 3),
+        // en passant takes the checking pawn
+        ("8/8/8/4k3/3Pp3/8/8/4K3 b - d3 0 1", 1),
+        // double check
+        ("4k3/8/8/8/1b6/8/8/r3K1N1 w - - 0 1", 1),
+    ];
+    for (fen_string, depth) in test_positions {
+        let mut position = Position::from_fen(fen_string);
+        let mut checked = 0;
+        walk_tree(depth, &mut position, &mut |position| {
+            if !position.in_check() {
+                return;
+            }
+            let moves = position.generate_legal_moves();
+            let evasions = position.generate_evasions();
+            assert_eq!(evasions.len(), moves.len(), "{}", position.to_fen());
+            assert!(moves.iter().all(|move_| evasions.contains(&move_)));
+            checked += 1;
+        });
+        assert!(checked > 0, "{fen_string}");
+    }
+}
+
+#[test]
+fn quiet_checks_match_checking_moves() {
+    let test_positions = [
+        (KIWIPETE.fen_string, 2),
+        (POSITION_3.fen_string, 3),
+        (POSITION_4.fen_string, 2),
+        (CHESS960_6.fen_string, 2),
+        // castling with check, and discovered checks by pawn, knight and king
+        ("3k4/8/8/8/8/8/8/R3K2R w KQ - 0 1", 0),
+        ("7k/8/8/8/8/2P5/8/B5K1 w - - 0 1", 0),
+        ("1k6/8/8/8/8/8/1N6/1R4K1 w - - 0 1", 0),
+        ("R2K3k/8/8/8/8/8/8/8 w - - 0 1", 0),
+    ];
+    for (fen_string, depth) in test_positions {
+        let mut position = Position::from_fen(fen_string);
+        let mut found = 0;
+        walk_tree(depth, &mut position, &mut |position| {
+            let quiet_checks = position.generate_quiet_checks();
+            let mut expected = 0;
+            for move_ in &position.generate_legal_moves() {
+                if move_.is_tactical() {
+                    continue;
+                }
+                let undo = position.make_move(&move_);
+                let gives_check = position.in_check();
+                position.unmake_move(&move_, undo);
+                if gives_check {
+                    assert!(quiet_checks.contains(&move_), "{}", position.to_fen());
+                    expected += 1;
+                }
+            }
+            assert_eq!(quiet_checks.len(), expected, "{}", position.to_fen());
+            found += expected;
+        });
+        assert!(found > 0, "{fen_string}");
+    }
+}
+
+#[test]
+fn gives_check_matches_making_the_move() {
+    let test_positions = [
+        (KIWIPETE.fen_string, 2),
+        (POSITION_3.fen_string, 3),
+        (POSITION_4.fen_string, 2),
+        (CHESS960_6.fen_string, 2),
+        // en passant opens the rank, promotions check along the line the pawn left
+        ("8/8/8/k2pP2R/8/8/8/7K w - d6 0 1", 0),
+        ("2k5/4P3/8/8/8/8/8/4K3 w - - 0 1", 0),
+        ("8/R3P2k/8/8/8/8/8/4K3 w - - 0 1", 0),
+        ("3k4/8/8/8/8/8/8/R3K2R w KQ - 0 1", 0),
+    ];
+    for (fen_string, depth) in test_positions {
+        let mut position = Position::from_fen(fen_string);
+        let mut checks = 0;
+        walk_tree(depth, &mut position, &mut |position| {
+            for move_ in &position.generate_legal_moves() {
+                let gives_check = position.gives_check(&move_);
+                let undo = position.make_move(&move_);
+                let in_check = position.in_check();
+                position.unmake_move(&move_, undo);
+                assert_eq!(
+                    gives_check,
+                    in_check,
+                    "{} {}",
+                    position.to_fen(),
+                    get_move_string(&move_)
+                );
+                checks += in_check as u32;
+            }
+        });
+        assert!(checks > 0, "{fen_string}");
+    }
+}
+
+#[test]
+fn discovered_checks_match_making_the_move() {
+    let test_positions = [
+        (KIWIPETE.fen_string, 2),
+        (POSITION_3.fen_string, 3),
+        (POSITION_4.fen_string, 2),
+        (CHESS960_6.fen_string, 2),
+        ("1k6/8/8/8/8/8/1N6/1R4K1 w - - 0 1", 0),
+        ("8/8/8/k2pP2R/8/8/8/7K w - d6 0 1", 0),
+        ("R2K3k/8/8/8/8/8/8/8 w - - 0 1", 0),
+    ];
+    for (fen_string, depth) in test_positions {
+        let mut position = Position::from_fen(fen_string);
+        let mut discovered_checks = 0;
+        walk_tree(depth, &mut position, &mut |position| {
+            for move_ in &position.generate_legal_moves() {
+                let is_discovered = position.is_discovered_check(&move_);
+                // a check by anything but the piece that landed, or the castled rook
+                let landed = if move_.is_castling() {
+                    get_castling_targets(&move_).1
+                } else {
+                    move_.to()
+                };
+                let undo = position.make_move(&move_);
+                let discovered = position.checkers() & !square_bit(landed.to_64()) != 0;
+                position.unmake_move(&move_, undo);
+                assert_eq!(
+                    is_discovered,
+                    discovered,
+                    "{} {}",
+                    position.to_fen(),
+                    get_move_string(&move_)
+                );
+                discovered_checks += discovered as u32;
+            }
+        });
+        if depth == 0 {
+            assert!(discovered_checks > 0, "{fen_string}");
+        }
+    }
+}
+
+#[test]
+fn discovered_double_and_enpassant_checks() {
+    let find_move = |position: &Position, move_string: &str| {
+        position
+            .generate_legal_moves()
+            .iter()
+            .find(|move_| get_move_string(move_) == move_string)
+            .expect("move is legal")
+    };
+    let check_kind = |fen: &str, move_string: &str| {
+        let position = Position::from_fen(fen);
+        let move_ = find_move(&position, move_string);
+        (
+            position.gives_check(&move_),
+            position.is_discovered_check(&move_),
+        )
+    };
+    // the knight uncovers the rook
+    assert_eq!(
+        check_kind("1k6/8/8/8/8/8/1N6/1R4K1 w - - 0 1", "b2d3"),
+        (true, true)
+    );
+    assert_eq!(
+        check_kind("1k6/8/8/8/8/8/1N6/1R4K1 w - - 0 1", "g1h1"),
+        (false, false)
+    );
+    // the bishop checks and uncovers the rook, a double check
+    let double = "1k6/8/8/8/8/8/1B6/1R4K1 w - - 0 1";
+    assert_eq!(check_kind(double, "b2e5"), (true, true));
+    let mut position = Position::from_fen(double);
+    position.make_move(&find_move(&position, "b2e5"));
+    assert_eq!(position.checkers().count_ones(), 2);
+    // taking en passant empties the rank between the rook and the king
+    assert_eq!(
+        check_kind("8/8/8/k2pP2R/8/8/8/7K w - d6 0 1", "e5d6"),
+        (true, true)
+    );
+    // a plain check is not a discovered one
+    let fools_mate = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2";
+    assert_eq!(check_kind(fools_mate, "d8h4"), (true, false));
+}
+
+#[test]
+fn captured_and_moved_pieces() {
+    let position = Position::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+    for move_ in &position.generate_legal_moves() {
+        let move_string = get_move_string(&move_);
+        let captured = position.captured_piece(&move_).map(|piece| piece.to_char());
+        let moved = position.moved_piece(&move_).map(|piece| piece.to_char());
+        match move_string.as_str() {
+            "e5d6" => assert_eq!((moved, captured), (Some('P'), Some('p'))),
+            "a1a8" | "h1h8" => assert_eq!((moved, captured), (Some('R'), Some('r'))),
+            // castling is encoded as the king taking its own rook
+            "e1h1" | "e1a1" => assert_eq!((moved, captured), (Some('K'), None)),
+            _ => assert_eq!(captured, None, "{move_string}"),
+        }
+        assert_eq!(move_.is_tactical(), captured.is_some(), "{move_string}");
+    }
+}
@@ -0,0 +1,249 @@
+use std::time::Duration;
+
+use rustchess::{
+    START_POSITION_FEN,
+    movegen::{Move, get_chess960_move_string},
+    pgn::{Game, GameResult, PgnError, PgnMove, PgnReader, SEVEN_TAG_ROSTER, engine_comment},
+    position::Position,
+    san::line_to_san,
+};
+
+fn read(pgn: &str) -> Vec<Result<Game, PgnError>> {
+    PgnReader::new(pgn.as_bytes()).collect()
+}
+
+fn mainline_san(game: &Game) -> String {
+    line_to_san(&game.start, &game.mainline().collect::<Vec<_>>())
+}
+
+fn moves(position: &Position, sans: &[&str]) -> Vec<Move> {
+    let mut position = *position;
+    sans.iter()
+        .map(|san| {
+            let move_ = position.parse_san(san).unwrap();
+            position.make_move(&move_);
+            move_
+        })
+        .collect()
+}
+
+// writes the game and reads it back
+fn round_trip(game: &Game) -> Game {
+    let pgn = game.to_pgn();
+    for line in pgn.lines() {
+        assert!(line.len() <= 80, "{line}");
+    }
+    let mut games = read(&pgn);
+    assert_eq!(games.len(), 1);
+    games.remove(0).unwrap()
+}
+
+const IMMORTAL: &str = r#"[Event "London casual game"]
+[Site "London"]
+[Date "1851.06.21"]
+[Round "?"]
+[White "Anderssen, Adolf"]
+[Black "Kieseritzky, Lionel"]
+[Result "1-0"]
+[ECO "C33"]
+[Annotator "Steinitz, \"W.\""]
+
+1.e4 e5 2.f4 exf4 3.Bc4 Qh4+ 4.Kf1 b5?! 5.Bxb5 Nf6 6.Nf3 Qh6 7.d3 Nh5 8.Nh4
+Qg5 9.Nf5 c6 10.g4 Nf6 11.Rg1! cxb5 12.h4 Qg6 13.h5 Qg5 14.Qf3 Ng8 15.Bxf4
+Qf6 16.Nc3 Bc5 17.Nd5 Qxb2 18.Bd6!! Bxg1 {It is from this move that Black's
+defeat stems. Wilhelm Steinitz suggested in 1879 that a better move would be
+18... Qxa1+; likely moves to follow are 19. Ke2 Qb2 20. Kd2 Bxg1.} 19.e5 Qxa1+
+20.Ke2 Na6 21.Nxg7+ Kd8 22.Qf6+ Nxf6 23.Be7# 1-0
+"#;
+
+#[test]
+fn test_tags_and_mainline() {
+    let games = read(IMMORTAL);
+    assert_eq!(games.len(), 1);
+    let game = games[0].as_ref().unwrap();
+
+    let roster: Vec<&str> = game.tags[..7]
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    assert_eq!(roster, SEVEN_TAG_ROSTER);
+    assert_eq!(game.tag("White"), Some("Anderssen, Adolf"));
+    assert_eq!(game.tag("ECO"), Some("C33"));
+    assert_eq!(game.tag("Annotator"), Some("Steinitz, \"W.\""));
+    assert_eq!(game.tag("PlyCount"), None);
+    assert_eq!(game.result, GameResult::WhiteWins);
+
+    assert_eq!(game.moves.len(), 45);
+    assert!(mainline_san(game).ends_with("Qf6+ Nxf6 Be7#"));
+    assert!(game.end().generate_legal_moves().is_empty());
+
+    // ?! ! and !! are kept as NAGs 6, 1 and 3
+    assert_eq!(game.moves[7].nags, [6]);
+    assert_eq!(game.moves[20].nags, [1]);
+    assert_eq!(game.moves[34].nags, [3]);
+    let comment = game.moves[35].comment.as_deref().unwrap();
+    assert!(comment.starts_with("It is from this move that Black's defeat stems."));
+    assert!(comment.ends_with("20. Kd2 Bxg1."));
+    assert!(!comment.contains('\n'));
+}
+
+#[test]
+fn test_variations_comments_and_nags() {
+    let pgn = "{Opening comment} 1. e4 $1 e5 {main} (1... c5 ; Sicilian\n\
+               2. Nf3 (2. c3 {Alapin}) 2... d6) (1... e6 2. d4) 2. Nf3 $14 *";
+    let games = read(pgn);
+    let game = games[0].as_ref().unwrap();
+    assert_eq!(game.result, GameResult::Unknown);
+    assert_eq!(mainline_san(game), "e4 e5 Nf3");
+
+    let e4 = &game.moves[0];
+    assert_eq!(e4.comment_before.as_deref(), Some("Opening comment"));
+    assert_eq!(e4.nags, [1]);
+    assert!(e4.variations.is_empty());
+
+    let e5 = &game.moves[1];
+    assert_eq!(e5.comment.as_deref(), Some("main"));
+    assert_eq!(e5.variations.len(), 2);
+    let sicilian = &e5.variations[0];
+    assert_eq!(sicilian.len(), 3);
+    assert_eq!(sicilian[0].comment.as_deref(), Some("Sicilian"));
+    // the nested variation replaces 2. Nf3, after 1... c5
+    let alapin = &sicilian[1].variations[0];
+    assert_eq!(get_chess960_move_string(&alapin[0].move_), "c2c3");
+    assert_eq!(alapin[0].comment.as_deref(), Some("Alapin"));
+    let french = &e5.variations[1];
+    assert_eq!(
+        french
+            .iter()
+            .map(|pgn_move| get_chess960_move_string(&pgn_move.move_))
+            .collect::<Vec<_>>(),
+        ["e7e6", "d2d4"]
+    );
+
+    assert_eq!(game.moves[2].nags, [14]);
+}
+
+#[test]
+fn test_several_games_and_setup() {
+    // the first game has no result in its movetext and ends at the next tags
+    let pgn = "[White \"A\"]\n[Result \"1/2-1/2\"]\n\n1. d4 d5\n\
+               [White \"B\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]\n\n\
+               1. e4 Kd7 2. e5 0-1\n\n\
+               % escaped line\n\
+               [White \"C\"]\n\n1-0\n";
+    let games: Vec<Game> = read(pgn).into_iter().map(Result::unwrap).collect();
+    assert_eq!(games.len(), 3);
+
+    assert_eq!(games[0].tag("White"), Some("A"));
+    assert_eq!(games[0].result, GameResult::Draw);
+    assert_eq!(mainline_san(&games[0]), "d4 d5");
+
+    assert_eq!(games[1].tag("White"), Some("B"));
+    assert_eq!(games[1].result, GameResult::BlackWins);
+    assert_eq!(mainline_san(&games[1]), "e4 Kd7 e5");
+    let end = games[1].end();
+    assert_eq!(
+        end.to_fen(),
+        Position::from_fen("8/3k4/8/4P3/8/8/8/4K3 b - - 0 2").to_fen()
+    );
+
+    assert_eq!(games[2].tag("White"), Some("C"));
+    assert!(games[2].moves.is_empty());
+    assert_eq!(games[2].result, GameResult::WhiteWins);
+}
+
+#[test]
+fn test_broken_games_are_skipped() {
+    let pgn = "[Event \"illegal\"]\n\n1. e4 e5 2. Ke3 Nc6 1-0\n\n\
+               [Event \"unclosed\"]\n\n1. e4 (1. d4 d5 *\n\n\
+               [Event \"bad fen\"]\n[FEN \"8/8/8 w - - 0 1\"]\n\n*\n\n\
+               [Event \"fine\"]\n\n1. e4 *\n";
+    let games = read(pgn);
+    assert_eq!(games.len(), 4);
+    assert!(matches!(games[0], Err(PgnError::InvalidMove(_))));
+    assert!(matches!(games[1], Err(PgnError::UnterminatedVariation)));
+    assert!(matches!(games[2], Err(PgnError::InvalidFen(_))));
+    assert_eq!(games[3].as_ref().unwrap().tag("Event"), Some("fine"));
+}
+
+#[test]
+fn test_many_games() {
+    let pgn = IMMORTAL.repeat(500);
+    let mut count = 0;
+    for game in PgnReader::new(pgn.as_bytes()) {
+        assert_eq!(game.unwrap().moves.len(), 45);
+        count += 1;
+    }
+    assert_eq!(count, 500);
+}
+
+#[test]
+fn test_write_engine_game() {
+    let start = Position::from_fen(START_POSITION_FEN);
+    let mut game = Game::from_moves(start, &moves(&start, &["f3", "e5", "g4", "Qh4#"]));
+    assert_eq!(game.result, GameResult::BlackWins);
+    game.set_tag("White", "rustchess");
+    game.set_tag("TimeControl", "40/60");
+    game.moves[1].comment = Some(engine_comment(35, 12, Duration::from_millis(1234)));
+    game.moves[3].comment = Some(engine_comment(-120, 7, Duration::from_millis(50)));
+    // the engine expected Nh3 instead of g4
+    let mut after_e5 = start;
+    for move_ in game.mainline().take(2) {
+        after_e5.make_move(&move_);
+    }
+    game.moves[2]
+        .variations
+        .push(PgnMove::line(&moves(&after_e5, &["Nh3", "d5"])));
+
+    assert_eq!(
+        game.to_pgn(),
+        "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+         [White \"rustchess\"]\n[Black \"?\"]\n[Result \"0-1\"]\n[TimeControl \"40/60\"]\n\n\
+         1. f3 e5 {+0.35/12 1.2s} 2. g4 (2. Nh3 d5) 2... Qh4# {-1.20/7 0.1s} 0-1\n\n"
+    );
+
+    let game = Game::from_moves(start, &moves(&start, &["Nf3", "Nf6", "Ng1", "Ng8"]));
+    assert_eq!(game.result, GameResult::Unknown);
+    let shuffle = moves(&start, &["Nf3", "Nf6", "Ng1", "Ng8"].repeat(2));
+    assert_eq!(Game::from_moves(start, &shuffle).result, GameResult::Draw);
+}
+
+#[test]
+fn test_write_round_trip() {
+    let game = read(IMMORTAL).remove(0).unwrap();
+    let written = round_trip(&game);
+    assert_eq!(written.tags, game.tags);
+    assert_eq!(written.moves, game.moves);
+    assert_eq!(written.result, game.result);
+    assert!(
+        game.to_pgn()
+            .contains("[Annotator \"Steinitz, \\\"W.\\\"\"]")
+    );
+
+    let pgn = "{Opening comment} 1. e4 $1 e5 {main} (1... c5 ; Sicilian\n\
+               2. Nf3 (2. c3 {Alapin}) 2... d6) (1... e6 2. d4) 2. Nf3 $14 *";
+    let game = read(pgn).remove(0).unwrap();
+    let written = round_trip(&game);
+    assert_eq!(written.moves, game.moves);
+    assert!(game.to_pgn().contains(
+        "{Opening comment} 1. e4 $1 e5 {main} (1... c5 {Sicilian} 2. Nf3 (2. c3 {Alapin})\n\
+         2... d6) (1... e6 2. d4) 2. Nf3 $14 *"
+    ));
+}
+
+#[test]
+fn test_write_setup_and_long_comments() {
+    let start = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 12");
+    let mut game = Game::from_moves(start, &moves(&start, &["Kd7", "e4", "Kc6"]));
+    let comment = "a long comment that goes on and on ".repeat(10);
+    game.moves[1].comment = Some(comment.clone());
+
+    let pgn = game.to_pgn();
+    assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 12\"]\n"));
+    assert!(pgn.contains("\n\n12... Kd7 13. e4 {a long"));
+    let written = round_trip(&game);
+    assert_eq!(written.start.to_fen(), start.to_fen());
+    assert_eq!(written.moves[1].comment.as_deref(), Some(comment.trim()));
+    assert_eq!(written.moves[2].move_, game.moves[2].move_);
+    assert!(pgn.contains("} 13... Kc6 *"));
+}
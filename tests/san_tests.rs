@@ -0,0 +1,187 @@
+use rustchess::{
+    START_POSITION_FEN,
+    movegen::get_chess960_move_string,
+    position::Position,
+    san::{MoveParseError, line_to_san},
+};
+
+fn san(fen: &str, move_string: &str) -> String {
+    let position = Position::from_fen(fen);
+    let move_ = position
+        .generate_legal_moves()
+        .iter()
+        .find(|move_| get_chess960_move_string(move_) == move_string)
+        .expect("move is legal");
+    position.move_to_san(&move_)
+}
+
+#[test]
+fn test_san_pieces_captures_and_promotions() {
+    assert_eq!(san(START_POSITION_FEN, "e2e4"), "e4");
+    assert_eq!(san(START_POSITION_FEN, "g1f3"), "Nf3");
+
+    let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -";
+    assert_eq!(san(kiwipete, "d5e6"), "dxe6");
+    assert_eq!(san(kiwipete, "e2a6"), "Bxa6");
+    assert_eq!(san(kiwipete, "e5f7"), "Nxf7");
+
+    let enpassant = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+    assert_eq!(san(enpassant, "e5d6"), "exd6");
+
+    let promotion = "3r4/4P3/8/8/8/8/k7/4K3 w - - 0 1";
+    assert_eq!(san(promotion, "e7e8q"), "e8=Q");
+    assert_eq!(san(promotion, "e7d8n"), "exd8=N");
+    assert_eq!(san(promotion, "e7d8r"), "exd8=R");
+}
+
+#[test]
+fn test_san_castling_and_suffixes() {
+    let castling = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+    assert_eq!(san(castling, "e1h1"), "O-O");
+    assert_eq!(san(castling, "e1a1"), "O-O-O");
+    assert_eq!(san(castling, "a1a8"), "Rxa8+");
+    // Chess960 castling with the rook right next to the king, on either side
+    assert_eq!(san("4k3/8/8/8/8/8/8/5KR1 w G - 0 1", "f1g1"), "O-O");
+    assert_eq!(san("4k3/8/8/8/8/8/8/5RK1 w F - 0 1", "g1f1"), "O-O-O");
+    // the castling rook gives check
+    assert_eq!(san("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1h1"), "O-O+");
+
+    let fools_mate = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2";
+    assert_eq!(san(fools_mate, "d8h4"), "Qh4#");
+}
+
+#[test]
+fn test_san_disambiguation() {
+    let rooks = "4k3/8/8/R7/8/8/4K3/R6R w - - 0 1";
+    assert_eq!(san(rooks, "a1d1"), "Rad1");
+    assert_eq!(san(rooks, "h1f1"), "Rhf1");
+    assert_eq!(san(rooks, "h1h5"), "Rhh5");
+    assert_eq!(san(rooks, "h1h2"), "Rh2");
+    assert_eq!(san(rooks, "a1a3"), "R1a3");
+    assert_eq!(san(rooks, "a5a3"), "R5a3");
+
+    let queens = "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1";
+    assert_eq!(san(queens, "a1b2"), "Qa1b2");
+    assert_eq!(san(queens, "c1b2"), "Qcb2");
+
+    // the pinned knight can't go to e2, so the other one needs no file
+    let pinned = "4k3/8/8/b7/8/2N3N1/8/4K3 w - - 0 1";
+    assert_eq!(san(pinned, "g3e2"), "Ne2");
+}
+
+#[test]
+fn test_line_to_san() {
+    let position = Position::from_fen(START_POSITION_FEN);
+    let mut moves = Vec::new();
+    let mut after = position;
+    for move_string in ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6", "e1h1"] {
+        let move_ = after
+            .generate_legal_moves()
+            .iter()
+            .find(|move_| get_chess960_move_string(move_) == move_string)
+            .unwrap();
+        after.make_move(&move_);
+        moves.push(move_);
+    }
+    assert_eq!(line_to_san(&position, &moves), "e4 e5 Nf3 Nc6 Bb5 a6 O-O");
+}
+
+fn parsed(fen: &str, san: &str) -> Result<String, MoveParseError> {
+    let position = Position::from_fen(fen);
+    position
+        .parse_san(san)
+        .map(|move_| get_chess960_move_string(&move_))
+}
+
+#[test]
+fn test_parse_san() {
+    let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -";
+    assert_eq!(parsed(START_POSITION_FEN, "e4"), Ok("e2e4".to_string()));
+    assert_eq!(parsed(START_POSITION_FEN, "Nf3"), Ok("g1f3".to_string()));
+    assert_eq!(parsed(kiwipete, "dxe6"), Ok("d5e6".to_string()));
+    assert_eq!(parsed(kiwipete, "Bxa6!?"), Ok("e2a6".to_string()));
+    assert_eq!(parsed(kiwipete, "O-O"), Ok("e1h1".to_string()));
+    assert_eq!(parsed(kiwipete, "O-O-O"), Ok("e1a1".to_string()));
+
+    let rooks = "4k3/8/8/R7/8/8/4K3/R6R w - - 0 1";
+    assert_eq!(parsed(rooks, "Rad1"), Ok("a1d1".to_string()));
+    assert_eq!(parsed(rooks, "R1a3"), Ok("a1a3".to_string()));
+    assert_eq!(parsed(rooks, "Ra1a3"), Ok("a1a3".to_string()));
+    assert_eq!(parsed(rooks, "Rhh5"), Ok("h1h5".to_string()));
+    assert_eq!(parsed(rooks, "Rh2"), Ok("h1h2".to_string()));
+}
+
+#[test]
+fn test_parse_loose_san() {
+    let enpassant = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+    assert_eq!(parsed(enpassant, "exd6 e.p."), Ok("e5d6".to_string()));
+    assert_eq!(parsed(enpassant, "exd6e.p."), Ok("e5d6".to_string()));
+    assert_eq!(parsed(enpassant, "ed6"), Ok("e5d6".to_string()));
+
+    let promotion = "3r4/4P3/8/8/8/8/k7/4K3 w - - 0 1";
+    for san in ["e8=Q", "e8Q", "e8q", "e8(Q)", "e8=Q+"] {
+        assert_eq!(parsed(promotion, san), Ok("e7e8q".to_string()), "{san}");
+    }
+    assert_eq!(parsed(promotion, "exd8=N"), Ok("e7d8n".to_string()));
+    assert_eq!(parsed(promotion, "e7xd8R"), Ok("e7d8r".to_string()));
+
+    let castling = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+    assert_eq!(parsed(castling, "0-0"), Ok("e1h1".to_string()));
+    assert_eq!(parsed(castling, "0-0-0"), Ok("e1a1".to_string()));
+    assert_eq!(parsed(castling, "Ra1xa8+"), Ok("a1a8".to_string()));
+    assert_eq!(parsed(castling, "Rxa8+!!"), Ok("a1a8".to_string()));
+    assert_eq!(
+        parsed(castling, "Ng1-f3"),
+        Err(MoveParseError::Illegal("Ng1-f3".to_string()))
+    );
+
+    let fools_mate = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2";
+    assert_eq!(parsed(fools_mate, "Qh4#"), Ok("d8h4".to_string()));
+    assert_eq!(parsed(fools_mate, "Qd8-h4#"), Ok("d8h4".to_string()));
+}
+
+#[test]
+fn test_parse_san_errors() {
+    let rooks = "4k3/8/8/R7/8/8/4K3/R6R w - - 0 1";
+    let error = |kind: fn(String) -> MoveParseError, san: &str| Err(kind(san.to_string()));
+    assert_eq!(
+        parsed(rooks, "Rd1"),
+        error(MoveParseError::Ambiguous, "Rd1")
+    );
+    assert_eq!(
+        parsed(rooks, "Ra3"),
+        error(MoveParseError::Ambiguous, "Ra3")
+    );
+    assert_eq!(parsed(rooks, "Rb2"), error(MoveParseError::Illegal, "Rb2"));
+    assert_eq!(parsed(rooks, "Nf3"), error(MoveParseError::Illegal, "Nf3"));
+    assert_eq!(parsed(rooks, "O-O"), error(MoveParseError::Illegal, "O-O"));
+    for san in ["", "Z", "Rz9", "Rh", "e8=X", "Ra1a1a3"] {
+        assert_eq!(
+            parsed(rooks, san),
+            error(MoveParseError::Invalid, san),
+            "{san}"
+        );
+    }
+    // the promotion piece can't be left out
+    let promotion = "3r4/4P3/8/8/8/8/k7/4K3 w - - 0 1";
+    assert_eq!(
+        parsed(promotion, "e8"),
+        error(MoveParseError::Illegal, "e8")
+    );
+}
+
+#[test]
+fn test_san_round_trip() {
+    let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -";
+    for fen in [
+        START_POSITION_FEN,
+        kiwipete,
+        "4k3/8/8/R7/8/8/4K3/R6R w - - 0 1",
+    ] {
+        let position = Position::from_fen(fen);
+        for move_ in &position.generate_legal_moves() {
+            let san = position.move_to_san(&move_);
+            assert_eq!(position.parse_san(&san), Ok(move_), "{san}");
+        }
+    }
+}